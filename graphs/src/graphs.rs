@@ -2,7 +2,8 @@
 use std::fmt::Debug;
 
 use std::marker::PhantomData;
-use std::collections::{HashSet, VecDeque, HashMap};
+use std::collections::{BinaryHeap, HashSet, VecDeque, HashMap};
+use std::cmp::Ordering;
 use std::hash::Hash;
 
 
@@ -69,15 +70,24 @@ pub type BFS<U : Clone, T : StateNode<U>> = AStar<U, T>;
 pub fn dfs<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug>(
     state: T,
 ) -> Option<Vec<U>> {
+    dfs_with_expansions(state).0
+}
+
+/// Same as *dfs* but also returns the number of states expanded during the search.
+pub fn dfs_with_expansions<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug>(
+    state: T,
+) -> (Option<Vec<U>>, usize) {
     let mut history: HashMap<T, (T, U)> = HashMap::new();
     let mut visited = HashSet::new();
     let mut f = VecDeque::new();
+    let mut expanded = 0;
     f.push_back(state.clone());
     while let Some(nouv) = f.pop_front() {
         //println!("hist len : {}", history.len());
         if nouv.end() {
-            return resolve_hist(state, nouv, history);
+            return (resolve_hist(state, nouv, history), expanded);
         } else {
+            expanded += 1;
             visited.insert(nouv.clone());
             for i in nouv.moves() {
                 let mut cop = nouv.clone();
@@ -89,7 +99,35 @@ pub fn dfs<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug>(
             }
         }
     }
-    return None;
+    (None, expanded)
+}
+
+#[derive(Debug)]
+/// A node waiting to be expanded, ordered so that the binary heap pops the lowest f_cost first.
+/// Among nodes with the same f_cost, the one furthest from the source is prefered.
+struct Candidate<T> {
+    f_cost: i32,
+    cost: i32,
+    state: T,
+}
+impl<T> PartialEq for Candidate<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.f_cost == other.f_cost && self.cost == other.cost
+    }
+}
+impl<T> Eq for Candidate<T> {}
+impl<T> PartialOrd for Candidate<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> Ord for Candidate<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .f_cost
+            .cmp(&self.f_cost)
+            .then(self.cost.cmp(&other.cost))
+    }
 }
 
 #[derive(Debug)]
@@ -97,7 +135,14 @@ pub fn dfs<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug>(
 pub struct AStar<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq> {
     source: T,
     f_costs: HashMap<T, i32>,
-    ongoing: HashSet<T>,
+    /// The nodes to expand, a node whose f_cost was lowered since it was pushed is skipped.
+    ongoing: BinaryHeap<Candidate<T>>,
+    /// The number of states expanded by the last call to *solve*.
+    expanded: usize,
+    /// The cost of a known solution, nodes that can not do better are not explored.
+    upper_bound: Option<i32>,
+    /// The number of nodes discarded by the upper bound during the last call to *solve*.
+    pruned: usize,
     phantom: PhantomData<U>,
 }
 impl<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug> AStar<U, T> {
@@ -106,44 +151,93 @@ impl<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug> AStar<U,
         AStar {
             source: source,
             f_costs: HashMap::new(),
-            ongoing: HashSet::new(),
+            ongoing: BinaryHeap::new(),
+            expanded: 0,
+            upper_bound: None,
+            pruned: 0,
             phantom: PhantomData,
         }
     }
-    /// Returns the best node, maximizing f_costs
-    pub fn best_node(&self) -> T {
-        self.ongoing
-            .iter()
-            .min_by_key(|k| self.f_costs.get(k))
-            .unwrap()
-            .clone()
+    /// Gives the cost of a known solution to the astar (branch and bound).
+    /// The nodes whose f_cost exceeds it are not explored, if the heuristic never overestimates
+    /// the remaining cost the solution found is still optimal.
+    pub fn upper_bound(&mut self, bound: i32) -> &mut Self {
+        self.upper_bound = Some(bound);
+        self
+    }
+    /// Removes and returns the best node, minimizing f_costs.
+    /// Returns None if there is no node left to expand.
+    pub fn best_node(&mut self) -> Option<T> {
+        while let Some(candidate) = self.ongoing.pop() {
+            if self.f_costs.get(&candidate.state) == Some(&candidate.f_cost) {
+                return Some(candidate.state);
+            }
+        }
+        None
+    }
+
+    /// Returns the number of states expanded by the last call to *solve*.
+    pub fn expansions(&self) -> usize {
+        self.expanded
+    }
+
+    /// Returns the number of nodes discarded by the upper bound during the last call to *solve*.
+    pub fn prunings(&self) -> usize {
+        self.pruned
+    }
+
+    /// Returns true if a node with the given f_cost can not lead to a solution better than the
+    /// upper bound.
+    fn exceeds_bound(&mut self, f_cost: i32) -> bool {
+        match self.upper_bound {
+            Some(bound) if f_cost > bound => {
+                self.pruned += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds the node reached with the given cost to the nodes to expand.
+    fn push(&mut self, state: T, cost: i32, f_cost: i32) {
+        self.f_costs.insert(state.clone(), f_cost);
+        self.ongoing.push(Candidate { f_cost, cost, state });
     }
 
     /// Uses astar to find the shortest way to the final state.
     pub fn solve(&mut self) -> Option<Vec<U>> {
+        self.expanded = 0;
+        self.pruned = 0;
+        self.f_costs.clear();
+        self.ongoing.clear();
         let source = self.source.clone();
-        self.ongoing.insert(source.clone());
+        let source_f_cost = source.dist_from_end();
+        if self.exceeds_bound(source_f_cost) {
+            return None;
+        }
+        self.push(source.clone(), 0, source_f_cost);
         let mut lowest_to = HashMap::new();
         lowest_to.insert(source.clone(), 0);
         let mut best_previous = HashMap::new();
-        while !self.ongoing.is_empty() {
-            let best_node = self.best_node();
+        while let Some(best_node) = self.best_node() {
             if best_node.end() {
                 return resolve_hist(source, best_node, best_previous);
             }
-            self.ongoing.remove(&best_node);
+            self.expanded += 1;
             for i in best_node.moves() {
                 let mut n = best_node.clone();
                 n.modify(&i);
                 let new_cost = lowest_to.get(&best_node).unwrap() + best_node.cost_to(&n, &i);
                 if !lowest_to.contains_key(&n) || new_cost < *lowest_to.get(&n).unwrap() {
+                    // The heuristic of the new node, the one of the expanded node could
+                    // overestimate the remaining cost.
+                    let f_cost = new_cost + n.dist_from_end();
+                    if self.exceeds_bound(f_cost) {
+                        continue;
+                    }
                     lowest_to.insert(n.clone(), new_cost);
                     best_previous.insert(n.clone(), (best_node.clone(), i));
-                    self.f_costs.insert(
-                        n.clone(),
-                        new_cost + best_node.dist_from_end(),
-                    );
-                    self.ongoing.insert(n.clone());
+                    self.push(n, new_cost, f_cost);
                 }
             }
         }
@@ -152,3 +246,61 @@ impl<U: Clone, T: StateNode<U> + Hash + Clone + PartialEq + Eq + Debug> AStar<U,
 }
 
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A node of a small weighted graph given by its edges (from, to, cost) and a heuristic for
+    /// every node. The last node is the end.
+    #[derive(Hash, Clone, PartialEq, Eq, Debug)]
+    struct Node {
+        id: usize,
+        edges: &'static [(usize, usize, i32)],
+        heuristic: &'static [i32],
+    }
+
+    impl StateNode<usize> for Node {
+        fn moves(&self) -> Vec<usize> {
+            self.edges.iter().filter(|e| e.0 == self.id).map(|e| e.1).collect()
+        }
+        fn modify(&mut self, mv: &usize) {
+            self.id = *mv;
+        }
+        fn end(&self) -> bool {
+            self.id == self.heuristic.len() - 1
+        }
+        fn cost_to(&self, target: &Self, _mv: &usize) -> i32 {
+            self.edges.iter().find(|e| e.0 == self.id && e.1 == target.id).unwrap().2
+        }
+        fn dist_from_end(&self) -> i32 {
+            self.heuristic[self.id]
+        }
+    }
+
+    /// A new node is scored with its own heuristic : scored with the heuristic of the node 1,
+    /// the node 2 would wait behind the end reached through the node 3 at a cost of 8.
+    #[test]
+    fn test_heuristic_of_new_node() {
+        let mut astar = AStar::new(Node {
+            id: 0,
+            edges: &[(0, 1, 1), (1, 2, 5), (2, 4, 1), (0, 3, 1), (3, 4, 7)],
+            heuristic: &[0, 6, 1, 0, 0],
+        });
+        assert_eq!(astar.solve(), Some(vec![1, 2, 4]));
+    }
+
+    /// The node 1 is first reached at a cost of 5, then at a cost of 2 through the node 2. Its
+    /// first entry in the heap is popped before the end but skipped, so it is expanded once.
+    #[test]
+    fn test_stale_entries() {
+        let mut astar = AStar::new(Node {
+            id: 0,
+            edges: &[(0, 1, 5), (0, 2, 1), (2, 1, 1), (1, 3, 10)],
+            heuristic: &[0, 0, 0, 0],
+        });
+        assert_eq!(astar.solve(), Some(vec![2, 1, 3]));
+        assert_eq!(astar.expansions(), 3);
+        assert_eq!(astar.best_node(), None);
+    }
+}
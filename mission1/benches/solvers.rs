@@ -0,0 +1,207 @@
+//! Benchmarks of the graphs solvers on seeded families of shippments of increasing sizes.
+//!
+//! Before the criterion timings, running the benchmarks writes mission1_solvers.csv in the
+//! target directory with the time, the number of expanded states and the length of the plan
+//! found by each solver on each instance.
+#[macro_use]
+extern crate criterion;
+extern crate graphs;
+extern crate mission1;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use criterion::{BenchmarkId, Criterion};
+use graphs::graphs::{dfs_with_expansions, AStar, StateNode};
+use mission1::heuristic::heur;
+use mission1::shippment::{Movement, Shippment};
+
+/// The seeds of the instances generated for each size.
+const SEEDS: [u32; 3] = [1, 2, 3];
+/// The sizes of the instances, see *instance*.
+const SIZES: [usize; 7] = [1, 2, 3, 4, 5, 6, 7];
+
+/// A family of instances, with the number of ports visited by the boat and the biggest size
+/// its instances are generated with.
+struct Family {
+    name: &'static str,
+    nb_ports: usize,
+    max_size: usize,
+}
+
+/// The rehandles make the a star explode from size 6 on voyages, and from size 8 the random
+/// instances of a single port take it minutes.
+const FAMILIES: [Family; 2] = [
+    Family { name: "single_port", nb_ports: 1, max_size: 7 },
+    Family { name: "voyage", nb_ports: 3, max_size: 5 },
+];
+
+/// Returns the sizes of the instances of the family the solver is run on.
+fn sizes(family: &Family, max_size: usize) -> Vec<usize> {
+    SIZES
+        .iter()
+        .cloned()
+        .filter(|&s| s <= family.max_size && s <= max_size)
+        .collect()
+}
+
+/// Generates the instance of the given family, size and seed, see *Shippment::new_seeded*.
+fn instance(family: &Family, size: usize, seed: u32) -> Shippment {
+    Shippment::new_seeded(size, family.nb_ports, seed)
+}
+
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// The shippment without the heuristic, solving it with an a star is a dijkstra.
+struct Uninformed(Shippment);
+
+impl StateNode<Movement> for Uninformed {
+    fn moves(&self) -> Vec<Movement> {
+        self.0.moves()
+    }
+    fn modify(&mut self, mv: &Movement) {
+        self.0.modify(mv)
+    }
+    fn end(&self) -> bool {
+        self.0.end()
+    }
+    fn cost_to(&self, target: &Self, mv: &Movement) -> i32 {
+        self.0.cost_to(&target.0, mv)
+    }
+}
+
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// The shippment without heuristic nor costs, solving it with an a star is a bfs.
+struct Unweighted(Shippment);
+
+impl StateNode<Movement> for Unweighted {
+    fn moves(&self) -> Vec<Movement> {
+        self.0.moves()
+    }
+    fn modify(&mut self, mv: &Movement) {
+        self.0.modify(mv)
+    }
+    fn end(&self) -> bool {
+        self.0.end()
+    }
+}
+
+/// The solvers compared, with the biggest size they are run on.
+/// Without heuristic the number of expanded states explodes from size 3, and the dfs wanders
+/// from size 7.
+const SOLVERS: [(&str, usize); 4] = [("astar", 7), ("dijkstra", 2), ("bfs", 2), ("dfs", 6)];
+
+/// Solves the shippment with the given solver.
+/// Returns the number of expanded states and the length of the plan found.
+fn solve(solver: &str, ship: &Shippment) -> (usize, Option<usize>) {
+    match solver {
+        "astar" => {
+            let mut a = AStar::new(ship.clone());
+            let sol = a.solve();
+            (a.expansions(), sol.map(|s| s.len()))
+        }
+        "dijkstra" => {
+            let mut a = AStar::new(Uninformed(ship.clone()));
+            let sol = a.solve();
+            (a.expansions(), sol.map(|s| s.len()))
+        }
+        "bfs" => {
+            let mut a = AStar::new(Unweighted(ship.clone()));
+            let sol = a.solve();
+            (a.expansions(), sol.map(|s| s.len()))
+        }
+        _ => {
+            let (sol, expanded) = dfs_with_expansions(ship.clone());
+            (expanded, sol.map(|s| s.len()))
+        }
+    }
+}
+
+/// Returns the path of the csv report, in the CARGO_TARGET_DIR directory if it is set, else in
+/// the target directory of the crate.
+fn report_path() -> PathBuf {
+    let dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target"));
+    dir.join("mission1_solvers.csv")
+}
+
+/// Solves every instance once with every solver and writes the results as csv in the file,
+/// creating its directory if needed.
+fn csv_report(path: &PathBuf) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = File::create(path)?;
+    writeln!(file, "family,size,seed,solver,time_ms,expansions,plan_length")?;
+    for family in FAMILIES.iter() {
+        for &(solver, max_size) in SOLVERS.iter() {
+            for size in sizes(family, max_size) {
+                for &seed in SEEDS.iter() {
+                    let ship = instance(family, size, seed);
+                    let start = Instant::now();
+                    let (expansions, length) = solve(solver, &ship);
+                    let time = start.elapsed();
+                    writeln!(
+                        file,
+                        "{},{},{},{},{:.3},{},{}",
+                        family.name,
+                        size,
+                        seed,
+                        solver,
+                        time.as_secs() as f64 * 1e3 + time.subsec_nanos() as f64 / 1e6,
+                        expansions,
+                        length.map(|l| l.to_string()).unwrap_or_default()
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Times every solver on every family and size.
+fn bench_solvers(c: &mut Criterion) {
+    for family in FAMILIES.iter() {
+        for &(solver, max_size) in SOLVERS.iter() {
+            let mut group = c.benchmark_group(format!("{}/{}", family.name, solver));
+            group.sample_size(10);
+            for size in sizes(family, max_size) {
+                let ships = SEEDS.iter().map(|&s| instance(family, size, s)).collect::<Vec<_>>();
+                group.bench_with_input(BenchmarkId::from_parameter(size), &ships, |b, ships| {
+                    b.iter(|| ships.iter().map(|s| solve(solver, s)).collect::<Vec<_>>())
+                });
+            }
+            group.finish();
+        }
+    }
+}
+
+/// Times the heuristic on the initial states of every family and size.
+fn bench_heuristic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("heur");
+    for family in FAMILIES.iter() {
+        for &size in SIZES.iter().filter(|&&s| s <= family.max_size) {
+            let ships = SEEDS.iter().map(|&s| instance(family, size, s)).collect::<Vec<_>>();
+            group.bench_with_input(BenchmarkId::new(family.name, size), &ships, |b, ships| {
+                b.iter(|| ships.iter().map(heur).sum::<i32>())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_solvers, bench_heuristic);
+
+/// Writes the csv report, then runs the criterion benchmarks.
+fn main() {
+    let path = report_path();
+    match csv_report(&path) {
+        Ok(()) => println!("csv report written to {}", path.display()),
+        Err(e) => eprintln!("could not write the csv report {} : {}", path.display(), e),
+    }
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -1,5 +1,4 @@
-/// The module in wich we can find the heuristic used in the a star algorithm
-///
+//! The module in wich we can find the heuristic used in the a star algorithm
 use shippment::{Container, Shippment};
 /// The currently used heuristic.
 pub fn heur(shippment : &Shippment) -> i32{
@@ -7,9 +6,9 @@ pub fn heur(shippment : &Shippment) -> i32{
     // the best way is to count exactly the number of boxes missing.
     for stack in shippment.stacks(){
         for cont in stack.iter(){
-            score += match cont {
-                &Container::Artillery => 1.0,
-                &Container::Munition => 1.0,
+            score += match cont.content {
+                Container::Artillery => 1.0,
+                Container::Munition => 1.0,
                 _ => 1.0,
 
             }
@@ -0,0 +1,14 @@
+//! The crate for mission1
+//! Contains the model of the shippment problem so it can be solved with the graphs crate.
+//! The shippment and its movements in the shippment.rs file.
+//! The emplacements of the boat in the place.rs file.
+//! The heuristic used by the a star in the heuristic.rs file.
+//! A plan validator and renderers in the validator.rs and render.rs files.
+#![deny(missing_docs)]
+extern crate graphs;
+extern crate rand;
+pub mod shippment;
+pub mod place;
+pub mod heuristic;
+pub mod validator;
+pub mod render;
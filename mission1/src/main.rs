@@ -1,27 +1,47 @@
-#[deny(missing_docs)]
-/// The crate for mission1
-/// Uses a star to find the way to ship all the containers in less possible moves.
+//! The crate for mission1
+//! Uses a star to find the way to ship all the containers in less possible moves.
+#![deny(missing_docs)]
 extern crate graphs;
+extern crate mission1;
 extern crate rand;
 extern crate time;
 
 use time::PreciseTime;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
 
 use graphs::graphs::*;
 
-mod shippment;
-mod place;
-mod heuristic;
-use shippment::{Shippment, print_sol};
+use mission1::shippment::{self, Shippment, print_sol, DEF_STACK_CAPACITY};
+use mission1::{render, validator};
 use rand::XorShiftRng;
 
-
-const MAX_PLACE : usize = 5;
-const DEF_NUM_PLACE : usize = 9;
-
 /// Launching it will print the results and the number of iterations to get these results
 ///
+/// Launching it with `validate <plan file> [<size> <seed> [<ports>]]` will instead replay the plan
+/// on the course example, or on the seeded instance of the given size, and report the first
+/// invalid movement.
+///
+/// Launching it with `render <plan file> <html file> [<size> <seed> [<ports>]]` will draw every
+/// step of the plan on the same instances and export it as an animated svg in the html file.
 fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() > 1 && args[1] == "validate" {
+        match args.len() {
+            3 | 5 | 6 => validate(&args[2], &instance(&args[3..])),
+            _ => usage("validate <plan file> [<size> <seed> [<ports>]]"),
+        }
+        return;
+    }
+    if args.len() > 1 && args[1] == "render" {
+        match args.len() {
+            4 | 6 | 7 => render_plan(&args[2], &args[3], &instance(&args[4..])),
+            _ => usage("render <plan file> <html file> [<size> <seed> [<ports>]]"),
+        }
+        return;
+    }
     let mut my_rand = XorShiftRng::new_unseeded();
 
     // Trying with the course example.
@@ -55,6 +75,36 @@ fn main() {
         solve_for(i, &mut my_rand);
         println!("\n");
     }
+
+    println!("\n\nNow trying voyages visiting several ports with a star\n\n");
+
+    for nb_ports in 2..5 {
+        println!("trying a voyage visiting {} ports with \n 6 emplacement of 3 containers on the boat\n\
+        3 stacks of 3 containers on the harbour, holding at most 4 containers.", nb_ports);
+        let mut ship = Shippment::new_random_voyage(6, 3, 3, nb_ports, &mut my_rand);
+        ship.set_place_capacity(3).set_stack_capacity(4);
+        let start = PreciseTime::now();
+        let sol = AStar::new(ship.clone()).solve();
+        let end = PreciseTime::now();
+        let diff = start.to(end).num_milliseconds();
+        let sol = match sol {
+            Some(sol) => sol,
+            None => {
+                println!(" found no solution in : {} ms, the capacities are too small.\n\n", diff);
+                continue;
+            }
+        };
+        println!(" found a solution in : {} ms", diff);
+        let mut loaded = ship;
+        for mv in sol.iter() {
+            loaded.modify(mv);
+        }
+        println!("{}", render::ascii(&loaded));
+        println!("the containers will be rehandled {} times during the voyage.", loaded.voyage_rehandles());
+        println!("the solution found is : \n\n");
+        print_sol(sol);
+        println!("\n\n");
+    }
 }
 
 /// Solves some random problems of the given size.
@@ -68,4 +118,83 @@ pub fn solve_for(i : usize, my_rand : &mut XorShiftRng){
     let end = PreciseTime::now();
     let diff = start.to(end).num_milliseconds();
     println!(" found all 500 solutions in : {} ms\n", diff);
-}
\ No newline at end of file
+}
+
+/// Prints how to use the command and exits with an error code.
+pub fn usage(command : &str) -> ! {
+    eprintln!("usage : {}", command);
+    process::exit(2);
+}
+
+/// Reads the plan in the given file.
+/// Exits with an error code if the plan can not be read.
+pub fn read_plan(path : &str) -> Vec<shippment::Movement> {
+    let mut text = String::new();
+    if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+        eprintln!("could not read {} : {}", path, e);
+        process::exit(2);
+    }
+    validator::parse_plan(&text).unwrap_or_else(|e| {
+        eprintln!("could not parse the plan : {}", e);
+        process::exit(2);
+    })
+}
+
+/// Returns the course example without arguments, else the seeded instance of the size, seed and
+/// number of ports given, see *Shippment::new_seeded*.
+/// Exits with an error code if an argument is not a number or if the stacks of the instance
+/// would be higher than the default stack capacity.
+pub fn instance(args : &[String]) -> Shippment {
+    if args.is_empty() {
+        return Shippment::new_course();
+    }
+    let numbers = args.iter().map(|a| a.parse::<u32>()).collect::<Result<Vec<u32>, _>>();
+    let n = numbers.unwrap_or_else(|e| {
+        eprintln!("invalid instance {} : {}", args.join(" "), e);
+        process::exit(2);
+    });
+    let size = n[0] as usize;
+    if size > DEF_STACK_CAPACITY {
+        eprintln!("invalid instance {} : the size can not exceed {}", args.join(" "), DEF_STACK_CAPACITY);
+        process::exit(2);
+    }
+    Shippment::new_seeded(size, n.get(2).map_or(1, |&p| p as usize), n[1])
+}
+
+/// Reads the plan in the given file and replays it on the given shippment.
+/// Exits with an error code if the plan can not be read or breaks a rule.
+pub fn validate(path : &str, ship : &Shippment) {
+    let plan = read_plan(path);
+    match validator::simulate(ship, &plan) {
+        Ok(simulation) => {
+            println!("the {} movements of the plan are valid.", plan.len());
+            if simulation.complete() {
+                println!("all the containers are on the boat.");
+            } else {
+                println!("some containers are still on the harbour.");
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("{} ({})", e, plan[e.step]);
+            process::exit(1);
+        }
+    }
+}
+
+/// Reads the plan in the given file, draws each of its steps on the given shippment and exports
+/// it to the html file.
+pub fn render_plan(path : &str, output : &str, ship : &Shippment) {
+    let plan = read_plan(path);
+    let simulation = validator::simulate(ship, &plan).unwrap_or_else(|e| {
+        eprintln!("{} ({})", e, plan[e.step]);
+        process::exit(1);
+    });
+    print!("{}", render::ascii_plan(&simulation, &plan));
+    let page = render::html(&simulation, &plan);
+    if let Err(e) = File::create(output).and_then(|mut f| f.write_all(page.as_bytes())) {
+        eprintln!("could not write {} : {}", output, e);
+        process::exit(2);
+    }
+    println!("the animated plan was written to {}", output);
+}
@@ -1,10 +1,22 @@
-use shippment::Container;
+//! The emplacements of the boat and the rules deciding wich containers they accept.
+use std::collections::BTreeSet;
+use shippment::{Cargo, Container};
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The reasons why an emplacement can refuse a container.
+pub enum Refusal {
+    /// The emplacement already holds as many containers as it can.
+    Full,
+    /// There is already a munition in the emplacement.
+    TwoMunitions,
+    /// Artillery can only be put at the bottom of an emplacement.
+    ArtilleryOnTop,
+}
 
-#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+#[derive(Hash, Clone, PartialEq, Eq, Debug, Default)]
 /// A emplacement on the boat.
 pub struct Place {
-    inner : Vec<Container>,
+    inner : Vec<Cargo>,
 }
 impl Place {
     /// Creates a new empty emplacement.
@@ -14,20 +26,36 @@ impl Place {
         }
     }
     /// Checks if the emplacement can accept the given container.
-    pub fn can_accept(&self, cont : &Container) -> bool{
-        match cont {
+    /// *capacity* is the maximum number of containers in an emplacement.
+    pub fn can_accept(&self, cont : &Cargo, capacity : usize) -> bool{
+        self.refusal(cont, capacity).is_none()
+    }
+    /// Returns the rule forbidding to put the given container on the emplacement, if any.
+    /// The capacity bounds every container, munitions included : they take as much room on the
+    /// boat as the others.
+    pub fn refusal(&self, cont : &Cargo, capacity : usize) -> Option<Refusal> {
+        if self.inner.len() >= capacity {
+            return Some(Refusal::Full);
+        }
+        match cont.content {
             // can not put two munitions at the same place.
-            &Container::Munition => !self.inner.iter().any(|c|c == &Container::Munition),
+            Container::Munition if self.inner.iter().any(|c|c.content == Container::Munition) => {
+                Some(Refusal::TwoMunitions)
+            }
             // can not put artillery on the top of anything.
-            &Container::Artillery => self.inner.is_empty(),
+            Container::Artillery if !self.inner.is_empty() => Some(Refusal::ArtilleryOnTop),
             // else we can put anything.
-            _ => self.inner.len()<::MAX_PLACE
+            _ => None,
         }
     }
     /// Push the given container to the emplacement.
-    pub fn push(&mut self, cont : Container) {
+    pub fn push(&mut self, cont : Cargo) {
         self.inner.push(cont);
     }
+    /// Returns the containers of the emplacement, from bottom to top.
+    pub fn containers(&self) -> &[Cargo] {
+        &self.inner
+    }
     /// Get the weight of the place.
     /// we consider that every container weights 1
     pub fn weight(&self) -> i32{
@@ -35,6 +63,60 @@ impl Place {
     }
     /// Checks if the place has the given container
     pub fn has(&self, cont : Container) -> bool{
-        self.inner.contains(&cont)
+        self.inner.iter().any(|c| c.content == cont)
+    }
+    /// Returns the number of different ports, before the given one, at wich containers of this
+    /// place will be unloaded.
+    /// A container for the given port put on top of this place will be in the way at each of them.
+    pub fn ports_before(&self, port : usize) -> usize {
+        let mut ports = self.inner
+            .iter()
+            .map(|c| c.port)
+            .filter(|p| *p < port)
+            .collect::<Vec<usize>>();
+        ports.sort();
+        ports.dedup();
+        ports.len()
+    }
+    /// Returns the number of times containers of this place are rehandled during the voyage.
+    /// A container is rehandled once at every earlier port having a container below it.
+    pub fn rehandles(&self) -> usize {
+        let mut ports_below = BTreeSet::new();
+        let mut rehandles = 0;
+        for c in self.inner.iter() {
+            rehandles += ports_below.range(..c.port).count();
+            ports_below.insert(c.port);
+        }
+        rehandles
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// From bottom to top, the containers go to the ports 2, 0, 1, 0 and 2.
+    fn voyage_place() -> Place {
+        let mut place = Place::new();
+        for &port in [2, 0, 1, 0, 2].iter() {
+            place.push(Cargo::new(Container::Rations, port));
+        }
+        place
+    }
+
+    #[test]
+    fn test_ports_before() {
+        let place = voyage_place();
+        assert_eq!(place.ports_before(0), 0);
+        assert_eq!(place.ports_before(2), 2);
+        assert_eq!(place.ports_before(3), 3);
+        assert_eq!(Place::new().ports_before(3), 0);
+    }
+
+    /// The container of the port 1 is rehandled at the port 0, the one on top at the ports 0
+    /// and 1.
+    #[test]
+    fn test_rehandles() {
+        assert_eq!(voyage_place().rehandles(), 3);
+        assert_eq!(Place::new().rehandles(), 0);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,239 @@
+//! Draws shippments in the terminal and exports plans as animated svg.
+//! The plans are drawn from their simulation, see *validator::simulate*.
+use shippment::{Cargo, Container, Movement, Shippment};
+use validator::Simulation;
+
+/// The size in pixels of a container in the svg export.
+const CELL: usize = 30;
+/// The number of seconds each step of a plan is shown in the svg export.
+const STEP_DURATION: usize = 1;
+
+/// Returns the text written in the cell of a container.
+/// The port is only written when the voyage visits several ports.
+fn label(cargo: &Cargo, voyage: bool) -> String {
+    if voyage {
+        format!("{}{}", cargo.content.letter(), cargo.port)
+    } else {
+        cargo.content.letter().to_string()
+    }
+}
+
+/// Returns true if the containers of the shippment are unloaded at several ports.
+fn is_voyage(ship: &Shippment) -> bool {
+    ship.stacks().iter().flat_map(|s| s.iter()).any(|c| c.port > 0) ||
+        (0..ship.nb_places()).any(|p| ship.place(p).containers().iter().any(|c| c.port > 0))
+}
+
+/// Draws columns of containers side by side, the bottom of the columns being aligned.
+/// Each column is labelled with its index under it.
+fn draw_columns(columns: &[&[Cargo]], height: usize, voyage: bool) -> String {
+    let width = if voyage { 4 } else { 3 };
+    let mut s = String::new();
+    for row in (0..height).rev() {
+        for col in columns {
+            match col.get(row) {
+                Some(c) => s.push_str(&format!("[{:^w$}]", label(c, voyage), w = width - 2)),
+                None => s.push_str(&" ".repeat(width)),
+            }
+        }
+        s.push('\n');
+    }
+    for i in 0..columns.len() {
+        s.push_str(&format!("{:^w$}", i, w = width));
+    }
+    s.push('\n');
+    s
+}
+
+/// Draws the stacks of the harbour and the emplacements of the boat in the terminal.
+pub fn ascii(ship: &Shippment) -> String {
+    let voyage = is_voyage(ship);
+    let stacks = (0..ship.nb_stacks()).map(|i| &ship.stack(i)[..]).collect::<Vec<&[Cargo]>>();
+    let places = (0..ship.nb_places())
+        .map(|i| ship.place(i).containers())
+        .collect::<Vec<&[Cargo]>>();
+    let stacks_height = stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+    let places_height = places.iter().map(|p| p.len()).max().unwrap_or(0);
+    format!(
+        "harbour :\n{}boat :\n{}",
+        draw_columns(&stacks, stacks_height, voyage),
+        draw_columns(&places, places_height, voyage)
+    )
+}
+
+/// Draws every state of the simulated plan in the terminal, each with the movement leading to it.
+pub fn ascii_plan(simulation: &Simulation, plan: &[Movement]) -> String {
+    let mut s = String::new();
+    for (step, state) in simulation.snapshots.iter().enumerate() {
+        match step {
+            0 => s.push_str("start :\n"),
+            _ => s.push_str(&format!("step {} : {}\n", step, plan[step - 1])),
+        }
+        s.push_str(&ascii(state));
+        s.push('\n');
+    }
+    s
+}
+
+/// Returns the color used to draw a container in the svg export.
+fn color(content: &Container) -> &'static str {
+    match *content {
+        Container::Munition => "#d9534f",
+        Container::Artillery => "#777777",
+        Container::LightFight => "#5cb85c",
+        Container::Rations => "#f0ad4e",
+    }
+}
+
+/// Draws a column of containers in svg, the bottom of the column being at *bottom*.
+fn svg_column(containers: &[Cargo], x: usize, bottom: usize, voyage: bool) -> String {
+    containers
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let y = bottom - (i + 1) * CELL;
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"black\"/>\
+                 <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" font-size=\"12\">{}</text>\n",
+                x,
+                y,
+                CELL,
+                CELL,
+                color(&c.content),
+                x + CELL / 2,
+                y + CELL / 2 + 4,
+                label(c, voyage)
+            )
+        })
+        .collect()
+}
+
+/// Exports the simulated plan as an animated svg.
+/// Every state of the shippment during the plan is shown for one second, the last one stays.
+pub fn svg(simulation: &Simulation, plan: &[Movement]) -> String {
+    let ship = &simulation.snapshots[0];
+    let voyage = is_voyage(ship);
+    let nb_stacks = ship.nb_stacks();
+    let nb_places = ship.nb_places();
+    let highest_stack = ship.stacks().iter().map(|s| s.len()).max().unwrap_or(0);
+    let height = ship.stack_capacity().max(ship.place_capacity()).max(highest_stack);
+    let boat_x = (nb_stacks + 1) * CELL;
+    let bottom = (height + 2) * CELL;
+    let width = boat_x + nb_places * CELL + CELL;
+    let mut s = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width,
+        bottom + 2 * CELL
+    );
+    s.push_str(&format!(
+        "<text x=\"0\" y=\"{}\">harbour</text><text x=\"{}\" y=\"{}\">boat</text>\n",
+        CELL / 2,
+        boat_x,
+        CELL / 2
+    ));
+    for i in 0..nb_stacks {
+        s.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            i * CELL + CELL / 2,
+            bottom + CELL / 2 + 4,
+            i
+        ));
+    }
+    for i in 0..nb_places {
+        s.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#3366cc\"/>\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+            boat_x + i * CELL,
+            bottom - ship.place_capacity() * CELL,
+            CELL,
+            ship.place_capacity() * CELL,
+            boat_x + i * CELL + CELL / 2,
+            bottom + CELL / 2 + 4,
+            i
+        ));
+    }
+    let last = simulation.snapshots.len() - 1;
+    for (step, state) in simulation.snapshots.iter().enumerate() {
+        let end = if step == last {
+            "fill=\"freeze\"".to_string()
+        } else {
+            format!("dur=\"{}s\"", STEP_DURATION)
+        };
+        s.push_str(&format!(
+            "<g visibility=\"hidden\"><set attributeName=\"visibility\" to=\"visible\" \
+             begin=\"{}s\" {}/>\n",
+            step * STEP_DURATION,
+            end
+        ));
+        let caption = match step {
+            0 => "start".to_string(),
+            _ => format!("step {} : {}", step, plan[step - 1]),
+        };
+        s.push_str(&format!(
+            "<text x=\"0\" y=\"{}\">{}</text>\n",
+            bottom + 3 * CELL / 2,
+            caption
+        ));
+        for i in 0..nb_stacks {
+            s.push_str(&svg_column(state.stack(i), i * CELL, bottom, voyage));
+        }
+        for i in 0..nb_places {
+            s.push_str(&svg_column(
+                state.place(i).containers(),
+                boat_x + i * CELL,
+                bottom,
+                voyage,
+            ));
+        }
+        s.push_str("</g>\n");
+    }
+    s.push_str("</svg>\n");
+    s
+}
+
+/// Exports the simulated plan as an html page containing the animated svg, for reports.
+pub fn html(simulation: &Simulation, plan: &[Movement]) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>shippment plan</title></head>\n\
+         <body>\n<p>{} movements, {} rehandles during the voyage.</p>\n{}</body>\n</html>\n",
+        plan.len(),
+        simulation.final_state().voyage_rehandles(),
+        svg(simulation, plan)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use validator::{parse_plan, simulate};
+
+    /// Loads the munition on top of the rations of a single stack to the single emplacement.
+    fn simulation() -> (Simulation, Vec<Movement>) {
+        let mut ship = Shippment::new(vec![vec![Container::Rations, Container::Munition]]);
+        ship.set_nb_places(1);
+        let plan = parse_plan("stack 0 to place 0").unwrap();
+        (simulate(&ship, &plan).unwrap(), plan)
+    }
+
+    #[test]
+    fn test_ascii_plan() {
+        let (simulation, plan) = simulation();
+        assert_eq!(
+            ascii_plan(&simulation, &plan),
+            "start :\nharbour :\n[M]\n[R]\n 0 \nboat :\n 0 \n\n\
+             step 1 : stack 0 to place 0\nharbour :\n[R]\n 0 \nboat :\n[M]\n 0 \n\n"
+        );
+    }
+
+    /// Each state is drawn once, the last one stays.
+    #[test]
+    fn test_html() {
+        let (simulation, plan) = simulation();
+        let page = html(&simulation, &plan);
+        assert!(page.contains("<p>1 movements, 0 rehandles during the voyage.</p>"));
+        assert_eq!(page.matches("<g visibility=\"hidden\">").count(), 2);
+        assert_eq!(page.matches("fill=\"freeze\"").count(), 1);
+        assert!(page.contains("step 1 : stack 0 to place 0"));
+        assert_eq!(page.matches(">M</text>").count(), 2);
+    }
+}
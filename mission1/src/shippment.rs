@@ -1,11 +1,18 @@
-/// This will use bfs to find, if possible,
-/// a way to ship all containers on the different places without putting anything
-/// on an artillery or two munitions together.
+//! This will use bfs to find, if possible,
+//! a way to ship all containers on the different places without putting anything
+//! on an artillery or two munitions together.
 use graphs::{StateNode};
-use rand::{XorShiftRng, Rng};
+use rand::{XorShiftRng, Rng, SeedableRng};
 use place::Place;
 use heuristic::heur;
 
+/// The default maximum number of containers in an emplacement of the boat.
+pub const DEF_PLACE_CAPACITY : usize = 5;
+/// The default maximum number of containers in a stack of the harbour.
+pub const DEF_STACK_CAPACITY : usize = 12;
+/// The default number of emplacements on the boat.
+pub const DEF_NUM_PLACE : usize = 9;
+
 #[derive(Hash, Clone, PartialEq, Eq, Debug)]
 /// The container : A container can contain Munition, Artillery, Light fight equipement and rations
 pub enum Container {
@@ -18,33 +25,73 @@ pub enum Container {
     /// The rations.
     Rations,
 }
+impl Container {
+    /// Returns the letter used to draw the container.
+    pub fn letter(&self) -> char {
+        match *self {
+            Container::Munition => 'M',
+            Container::Artillery => 'A',
+            Container::LightFight => 'L',
+            Container::Rations => 'R',
+        }
+    }
+}
 /// Creates a new random container.
 pub fn new_container(rand : &mut XorShiftRng) -> Container {
     rand.choose(&[Container::Munition, Container::Artillery, Container::LightFight, Container::Rations]).unwrap().clone()
 }
 
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// A container with the port of the voyage at wich it must be unloaded.
+/// Ports are numbered in the order the boat visits them, starting at 0.
+pub struct Cargo {
+    /// What is inside the container.
+    pub content: Container,
+    /// The port at wich the container is unloaded.
+    pub port: usize,
+}
+impl Cargo {
+    /// Creates a new cargo going to the given port.
+    pub fn new(content: Container, port: usize) -> Self {
+        Cargo { content, port }
+    }
+    /// Creates a new random cargo going to one of the *nb_ports* first ports.
+    /// With only one port, no port is drawn so the random generator is used as for a single
+    /// harbour shippment.
+    pub fn new_random(rand : &mut XorShiftRng, nb_ports : usize) -> Self {
+        let content = new_container(rand);
+        let port = if nb_ports > 1 { rand.gen_range(0, nb_ports) } else { 0 };
+        Cargo::new(content, port)
+    }
+}
+
+/// The index of a stack on the harbour.
+pub type StackIndex = usize;
+/// The index of an emplacement on the boat.
+pub type PlaceIndex = usize;
+
 #[derive(Clone, Debug)]
 /// A possible movement made by the crane.
 pub enum Movement{
     /// The crane can move a container from a stack to another stack.
-    StackToStack(usize, usize),
+    StackToStack(StackIndex, StackIndex),
     /// The crane can move a container from a stack to a place in the boat.
-    StackToTarget(usize, usize),
+    StackToTarget(StackIndex, PlaceIndex),
 }
 
 /// The trait of the stack,
 pub trait StackTrait {
     /// The stacks can be created randomly.
-    fn new_random(rand : &mut XorShiftRng, size : usize)-> Self;
+    fn new_random(rand : &mut XorShiftRng, size : usize, nb_ports : usize)-> Self;
 }
 /// A stack is just a vector of containers.
-pub type Stack  = Vec<Container>;
+pub type Stack  = Vec<Cargo>;
 
 impl StackTrait for Stack {
     /// Creates a new random stacks of the desired size with the given random
-    /// number generator.
-    fn new_random(mut rand : &mut XorShiftRng, size : usize) -> Self {
-        (0..size).map(|_|new_container(&mut rand)).collect()
+    /// number generator, the containers go to one of the *nb_ports* first ports.
+    fn new_random(rand : &mut XorShiftRng, size : usize, nb_ports : usize) -> Self {
+        (0..size).map(|_|Cargo::new_random(rand, nb_ports)).collect()
     }
 }
 
@@ -52,7 +99,11 @@ impl StackTrait for Stack {
 /// The shippment, contains the stacks of containers and the places in the boat.
 pub struct Shippment {
     places: Vec<Place>,
-    stacks: Vec<Vec<Container>>,
+    stacks: Vec<Stack>,
+    /// The maximum number of containers in a stack of the harbour.
+    stack_capacity: usize,
+    /// The maximum number of containers in an emplacement of the boat.
+    place_capacity: usize,
 }
 
 impl Shippment {
@@ -108,19 +159,104 @@ impl Shippment {
     /// nb_places : the number of emplacment on the boat.
     /// nb_stack : the number of stacks.
     /// stacks_size : the size of the stacks.
-    pub fn new_random(nb_places : usize, nb_stack : usize, stacks_size : usize, mut rand : &mut XorShiftRng) -> Self {
+    pub fn new_random(nb_places : usize, nb_stack : usize, stacks_size : usize, rand : &mut XorShiftRng) -> Self {
+        Shippment::new_random_voyage(nb_places, nb_stack, stacks_size, 1, rand)
+    }
+    /// Creates a new random shippment for a voyage visiting *nb_ports* ports.
+    /// Every container is sent to a random port of the voyage.
+    /// Panics if the stacks are higher than the default stack capacity.
+    pub fn new_random_voyage(nb_places : usize, nb_stack : usize, stacks_size : usize, nb_ports : usize, rand : &mut XorShiftRng) -> Self {
+        assert!(stacks_size <= DEF_STACK_CAPACITY, "a stack holds more than {} containers", DEF_STACK_CAPACITY);
         Shippment {
             places: (0..nb_places).map(|_| Place::new()).collect(),
-            stacks: (0..nb_stack).map(|_| Stack::new_random(&mut rand, stacks_size)).collect(),
+            stacks: (0..nb_stack).map(|_| Stack::new_random(rand, stacks_size, nb_ports)).collect(),
+            stack_capacity: DEF_STACK_CAPACITY,
+            place_capacity: DEF_PLACE_CAPACITY,
         }
     }
+    /// Creates the random instance of the given size and seed for a voyage visiting *nb_ports*
+    /// ports, the same on every run.
+    /// An instance of size *n* has *n* stacks of *n* containers and *2n* emplacements on the boat.
+    pub fn new_seeded(size : usize, nb_ports : usize, seed : u32) -> Self {
+        let mut rand = XorShiftRng::from_seed([seed, 0x9E37_79B9, 0x7F4A_7C15, 0x94D0_49BB]);
+        Shippment::new_random_voyage(2 * size, size, size, nb_ports, &mut rand)
+    }
     /// Creates a new shippment with the given stats and the default number of emplacement in the boat
+    /// All the containers are unloaded at the same port.
     pub fn new( stacks: Vec<Vec<Container>>) -> Self {
+        Shippment::new_voyage(
+            stacks
+                .into_iter()
+                .map(|s| s.into_iter().map(|c| Cargo::new(c, 0)).collect())
+                .collect(),
+        )
+    }
+    /// Creates a new shippment for a voyage with the given stacks and the default number of
+    /// emplacement in the boat.
+    /// Panics if a stack holds more containers than the default stack capacity.
+    pub fn new_voyage( stacks: Vec<Stack>) -> Self {
+        assert!(
+            stacks.iter().all(|s| s.len() <= DEF_STACK_CAPACITY),
+            "a stack holds more than {} containers", DEF_STACK_CAPACITY
+        );
         Shippment {
-            places: (0..::DEF_NUM_PLACE).map(|_| Place::new()).collect(),
-            stacks: stacks,
+            places: (0..DEF_NUM_PLACE).map(|_| Place::new()).collect(),
+            stacks,
+            stack_capacity: DEF_STACK_CAPACITY,
+            place_capacity: DEF_PLACE_CAPACITY,
         }
     }
+    /// Sets the maximum number of containers in a stack of the harbour.
+    /// The crane will not put a container on a stack having already this number of containers.
+    /// Panics if a stack already holds more containers.
+    pub fn set_stack_capacity(&mut self, capacity: usize) -> &mut Self {
+        assert!(
+            self.stacks.iter().all(|s| s.len() <= capacity),
+            "a stack holds more than {} containers", capacity
+        );
+        self.stack_capacity = capacity;
+        self
+    }
+    /// Sets the maximum number of containers in an emplacement of the boat.
+    /// Panics if an emplacement already holds more containers.
+    pub fn set_place_capacity(&mut self, capacity: usize) -> &mut Self {
+        assert!(
+            self.places.iter().all(|p| p.containers().len() <= capacity),
+            "an emplacement holds more than {} containers", capacity
+        );
+        self.place_capacity = capacity;
+        self
+    }
+    /// Sets the number of emplacements on the boat.
+    /// The emplacements are recreated empty.
+    pub fn set_nb_places(&mut self, nb_places: usize) -> &mut Self {
+        self.places = (0..nb_places).map(|_| Place::new()).collect();
+        self
+    }
+    /// Returns the maximum number of containers in a stack of the harbour.
+    pub fn stack_capacity(&self) -> usize {
+        self.stack_capacity
+    }
+    /// Returns the maximum number of containers in an emplacement of the boat.
+    pub fn place_capacity(&self) -> usize {
+        self.place_capacity
+    }
+    /// Returns the number of stacks on the harbour.
+    pub fn nb_stacks(&self) -> usize {
+        self.stacks.len()
+    }
+    /// Returns the number of emplacements on the boat.
+    pub fn nb_places(&self) -> usize {
+        self.places.len()
+    }
+    /// Returns the stack at the given index.
+    pub fn stack(&self, index: StackIndex) -> &Stack {
+        &self.stacks[index]
+    }
+    /// Returns the emplacement at the given index.
+    pub fn place(&self, index: PlaceIndex) -> &Place {
+        &self.places[index]
+    }
     /// Returns the weight of the heavier emplacement.
     pub fn max_weight(&self) -> i32 {
         self.places.iter().map(|p|p.weight()).max().unwrap()
@@ -130,9 +266,18 @@ impl Shippment {
         self.places.clone()
     }
     /// Returns a copy of the stacks.
-    pub fn stacks(&self) -> Vec<Vec<Container>> {
+    pub fn stacks(&self) -> Vec<Stack> {
         self.stacks.clone()
     }
+    /// Returns the number of containers that will be rehandled during the voyage
+    /// if the boat is unloaded as it is now.
+    ///
+    /// At each port, the containers on top of the ones to unload are taken off and put back in
+    /// the same order. Taking a container off and putting it back counts as one rehandle, the
+    /// unit of the costs of the a star.
+    pub fn voyage_rehandles(&self) -> usize {
+        self.places.iter().map(|p| p.rehandles()).sum()
+    }
 }
 
 impl StateNode<Movement> for Shippment {
@@ -141,12 +286,14 @@ impl StateNode<Movement> for Shippment {
         let mut res = vec![];
         for (id_stack, stack) in self.stacks.iter().enumerate() {
             for (id_target, target) in self.places.iter().enumerate() {
-                if !stack.is_empty() && target.can_accept(stack.last().unwrap()) {
+                if !stack.is_empty() && target.can_accept(stack.last().unwrap(), self.place_capacity) {
                     res.push(Movement::StackToTarget(id_stack, id_target));
                 }
             }
             for (id_second_stack, second_stack) in self.stacks.iter().enumerate(){
-                if !stack.is_empty() && second_stack != stack && second_stack.len()<12 {
+                if !stack.is_empty() && id_second_stack != id_stack &&
+                    second_stack.len() < self.stack_capacity
+                {
                     res.push(Movement::StackToStack(id_stack, id_second_stack));
                 }
             }
@@ -171,6 +318,19 @@ impl StateNode<Movement> for Shippment {
         self.stacks.iter().all(|s| s.is_empty())
     }
 
+    /// Every move of the crane at the harbour costs one, and so does every rehandle during the
+    /// voyage : loading a container also costs one for every port at wich it will be in the way.
+    /// The cost of a plan is thus its length plus the *voyage_rehandles* of the loaded boat.
+    fn cost_to(&self, _target: &Self, mv: &Movement) -> i32 {
+        match *mv {
+            Movement::StackToStack(_, _) => 1,
+            Movement::StackToTarget(stack, target) => {
+                let cargo = self.stacks[stack].last().unwrap();
+                1 + self.places[target].ports_before(cargo.port) as i32
+            }
+        }
+    }
+
     /// sums the size of the stacks
     fn dist_from_end(&self)-> i32 {
         heur(self)
@@ -189,3 +349,97 @@ pub fn print_sol(sol : Vec<Movement>) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphs::graphs::AStar;
+
+    /// Returns the cost of the plan according to the a star.
+    fn plan_cost(ship: &Shippment, plan: &[Movement]) -> i32 {
+        let mut state = ship.clone();
+        let mut total = 0;
+        for mv in plan {
+            let mut next = state.clone();
+            next.modify(mv);
+            total += state.cost_to(&next, mv);
+            state = next;
+        }
+        total
+    }
+
+    /// The cost of a plan is its number of moves plus the rehandles of the voyage.
+    #[test]
+    fn test_cost_in_rehandles() {
+        let mut ship = Shippment::new_voyage(vec![
+            vec![Cargo::new(Container::Rations, 0)],
+            vec![Cargo::new(Container::LightFight, 1)],
+        ]);
+        ship.set_nb_places(1);
+        let plans = [
+            vec![Movement::StackToTarget(0, 0), Movement::StackToTarget(1, 0)],
+            vec![Movement::StackToTarget(1, 0), Movement::StackToTarget(0, 0)],
+            vec![
+                Movement::StackToStack(1, 0),
+                Movement::StackToTarget(0, 0),
+                Movement::StackToTarget(0, 0),
+            ],
+        ];
+        let mut rehandles = vec![];
+        for plan in plans.iter() {
+            let mut loaded = ship.clone();
+            for mv in plan {
+                loaded.modify(mv);
+            }
+            assert!(loaded.end());
+            rehandles.push(loaded.voyage_rehandles());
+            assert_eq!(plan_cost(&ship, plan), (plan.len() + loaded.voyage_rehandles()) as i32);
+        }
+        assert_eq!(rehandles, vec![1, 0, 0]);
+        let best = AStar::new(ship.clone()).solve().unwrap();
+        assert_eq!(plan_cost(&ship, &best), 2);
+    }
+
+    /// Returns the stack to stack moves as (from, to) pairs.
+    fn stack_moves(ship: &Shippment) -> Vec<(StackIndex, StackIndex)> {
+        ship.moves()
+            .into_iter()
+            .filter_map(|mv| match mv {
+                Movement::StackToStack(from, to) => Some((from, to)),
+                Movement::StackToTarget(_, _) => None,
+            })
+            .collect()
+    }
+
+    /// Two stacks with the same content are still different stacks.
+    #[test]
+    fn test_equal_stacks() {
+        let ship = Shippment::new(vec![vec![Container::Rations], vec![Container::Rations]]);
+        assert_eq!(stack_moves(&ship), vec![(0, 1), (1, 0)]);
+    }
+
+    /// The crane fills neither the stacks nor the emplacements beyond their capacity, whatever
+    /// the container.
+    #[test]
+    fn test_capacities() {
+        let mut ship = Shippment::new(vec![vec![Container::Rations], vec![Container::Munition]]);
+        ship.set_nb_places(1).set_stack_capacity(1).set_place_capacity(1);
+        assert!(stack_moves(&ship).is_empty());
+        ship.modify(&Movement::StackToTarget(0, 0));
+        assert_eq!(stack_moves(&ship), vec![(1, 0)]);
+        assert_eq!(ship.moves().len(), 1);
+        assert_eq!(
+            ship.place(0).refusal(ship.stack(1).last().unwrap(), 1),
+            Some(::place::Refusal::Full)
+        );
+        assert!(AStar::new(ship.clone()).solve().is_none());
+        ship.set_place_capacity(2);
+        assert_eq!(ship.moves().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "a stack holds more than 1 containers")]
+    fn test_stack_capacity_too_small() {
+        Shippment::new(vec![vec![Container::Rations, Container::Rations]]).set_stack_capacity(1);
+    }
+}
@@ -0,0 +1,242 @@
+//! Replays plans made of crane movements on a shippment.
+//! This allows to check plans written by hand or found by another solver,
+//! independently of the graphs algorithms.
+use std::fmt;
+use std::str::FromStr;
+use graphs::StateNode;
+use place::Refusal;
+use shippment::{Movement, Shippment, StackIndex, PlaceIndex};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The rules a movement can violate.
+pub enum Violation {
+    /// The movement takes a container from a stack that does not exist.
+    UnknownStack(StackIndex),
+    /// The movement brings a container to an emplacement that does not exist.
+    UnknownPlace(PlaceIndex),
+    /// The movement takes a container from an empty stack.
+    EmptyStack(StackIndex),
+    /// The movement puts a container back on the stack it comes from.
+    SameStack(StackIndex),
+    /// The destination stack already holds as many containers as the harbour allows.
+    StackFull(StackIndex),
+    /// The emplacement refused the container.
+    Refused(PlaceIndex, Refusal),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::UnknownStack(s) => write!(f, "there is no stack {}", s),
+            Violation::UnknownPlace(p) => write!(f, "there is no emplacement {}", p),
+            Violation::EmptyStack(s) => write!(f, "stack {} is empty", s),
+            Violation::SameStack(s) => write!(f, "can not move a container from stack {} to itself", s),
+            Violation::StackFull(s) => write!(f, "stack {} is full", s),
+            Violation::Refused(p, Refusal::Full) => write!(f, "emplacement {} is full", p),
+            Violation::Refused(p, Refusal::TwoMunitions) => {
+                write!(f, "emplacement {} already contains a munition", p)
+            }
+            Violation::Refused(p, Refusal::ArtilleryOnTop) => {
+                write!(f, "artillery must be at the bottom of emplacement {}", p)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The error returned when a plan can not be replayed.
+pub struct PlanError {
+    /// The index of the first invalid movement in the plan.
+    pub step: usize,
+    /// The rule violated by this movement.
+    pub violation: Violation,
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "step {} is invalid : {}", self.step, self.violation)
+    }
+}
+
+/// The result of a successful replay.
+pub struct Simulation {
+    /// The state of the shippment before the plan and after each movement.
+    pub snapshots: Vec<Shippment>,
+}
+
+impl Simulation {
+    /// Returns the state of the shippment once the whole plan was executed.
+    pub fn final_state(&self) -> &Shippment {
+        self.snapshots.last().unwrap()
+    }
+    /// Returns true if the plan loaded every container on the boat.
+    pub fn complete(&self) -> bool {
+        self.final_state().end()
+    }
+}
+
+/// Checks that the given movement can be made on the shippment.
+pub fn check(ship: &Shippment, mv: &Movement) -> Result<(), Violation> {
+    let stack = match *mv {
+        Movement::StackToStack(s, _) | Movement::StackToTarget(s, _) => s,
+    };
+    let nb_stacks = ship.nb_stacks();
+    if stack >= nb_stacks {
+        return Err(Violation::UnknownStack(stack));
+    }
+    let cargo = match ship.stack(stack).last() {
+        Some(c) => c,
+        None => return Err(Violation::EmptyStack(stack)),
+    };
+    match *mv {
+        Movement::StackToStack(_, target) => {
+            if target >= nb_stacks {
+                Err(Violation::UnknownStack(target))
+            } else if target == stack {
+                Err(Violation::SameStack(stack))
+            } else if ship.stack(target).len() >= ship.stack_capacity() {
+                Err(Violation::StackFull(target))
+            } else {
+                Ok(())
+            }
+        }
+        Movement::StackToTarget(_, target) => {
+            if target >= ship.nb_places() {
+                return Err(Violation::UnknownPlace(target));
+            }
+            match ship.place(target).refusal(cargo, ship.place_capacity()) {
+                Some(refusal) => Err(Violation::Refused(target, refusal)),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+/// Replays the plan on the given shippment.
+/// Returns every intermediate state or the first movement breaking a rule.
+pub fn simulate(ship: &Shippment, plan: &[Movement]) -> Result<Simulation, PlanError> {
+    let mut snapshots = vec![ship.clone()];
+    for (step, mv) in plan.iter().enumerate() {
+        let mut next = snapshots.last().unwrap().clone();
+        if let Err(violation) = check(&next, mv) {
+            return Err(PlanError { step, violation });
+        }
+        next.modify(mv);
+        snapshots.push(next);
+    }
+    Ok(Simulation { snapshots })
+}
+
+impl fmt::Display for Movement {
+    /// Writes the movement in the format read by *parse_plan*.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Movement::StackToStack(s1, s2) => write!(f, "stack {} to stack {}", s1, s2),
+            Movement::StackToTarget(s, t) => write!(f, "stack {} to place {}", s, t),
+        }
+    }
+}
+
+impl FromStr for Movement {
+    type Err = String;
+    /// Reads a movement written as "stack 0 to stack 1" or "stack 0 to place 3".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<&str>>();
+        let index = |w: &str| w.parse::<usize>().map_err(|_| format!("invalid index : {}", w));
+        match words.as_slice() {
+            ["stack", from, "to", "stack", to] => Ok(Movement::StackToStack(index(from)?, index(to)?)),
+            ["stack", from, "to", "place", to] => Ok(Movement::StackToTarget(index(from)?, index(to)?)),
+            _ => Err(format!("invalid movement : {}", s)),
+        }
+    }
+}
+
+/// Reads a plan, one movement per line.
+/// Empty lines and lines starting with '#' are ignored.
+pub fn parse_plan(text: &str) -> Result<Vec<Movement>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, l)| (i, l.trim()))
+        .filter(|&(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(i, l)| l.parse().map_err(|e| format!("line {} : {}", i + 1, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphs::graphs::AStar;
+    use shippment::Container;
+
+    /// Returns the violation of the single movement written in the text.
+    fn violation(ship: &Shippment, mv: &str) -> Violation {
+        check(ship, &mv.parse().unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn test_violations() {
+        let course = Shippment::new_course();
+        assert_eq!(violation(&course, "stack 9 to stack 0"), Violation::UnknownStack(9));
+        assert_eq!(violation(&course, "stack 0 to stack 9"), Violation::UnknownStack(9));
+        assert_eq!(violation(&course, "stack 0 to place 9"), Violation::UnknownPlace(9));
+        assert_eq!(violation(&course, "stack 0 to stack 0"), Violation::SameStack(0));
+        let mut full = course.clone();
+        full.set_stack_capacity(8);
+        assert_eq!(violation(&full, "stack 0 to stack 1"), Violation::StackFull(1));
+        let empty = Shippment::new(vec![vec![], vec![Container::Rations]]);
+        assert_eq!(violation(&empty, "stack 0 to stack 1"), Violation::EmptyStack(0));
+
+        let mut artillery = course.clone();
+        artillery.modify(&"stack 0 to place 0".parse().unwrap());
+        assert_eq!(
+            violation(&artillery, "stack 1 to place 0"),
+            Violation::Refused(0, Refusal::ArtilleryOnTop)
+        );
+        artillery.set_place_capacity(1);
+        assert_eq!(violation(&artillery, "stack 1 to place 0"), Violation::Refused(0, Refusal::Full));
+        let mut munitions = Shippment::new(vec![vec![Container::Munition, Container::Munition]]);
+        munitions.modify(&"stack 0 to place 4".parse().unwrap());
+        assert_eq!(
+            violation(&munitions, "stack 0 to place 4"),
+            Violation::Refused(4, Refusal::TwoMunitions)
+        );
+    }
+
+    /// The error gives the index of the first invalid movement.
+    #[test]
+    fn test_plan_error() {
+        let plan = parse_plan("stack 0 to place 0\nstack 1 to place 1\nstack 2 to place 0").unwrap();
+        let err = simulate(&Shippment::new_course(), &plan).err().unwrap();
+        assert_eq!(err, PlanError { step: 2, violation: Violation::Refused(0, Refusal::ArtilleryOnTop) });
+        let simulation = simulate(&Shippment::new_course(), &plan[..2]).unwrap();
+        assert_eq!(simulation.snapshots.len(), 3);
+        assert!(!simulation.complete());
+    }
+
+    /// The lines are numbered in the file, comments and blank lines included.
+    #[test]
+    fn test_parse_plan() {
+        assert_eq!(parse_plan("# a plan\n\n  stack 0 to place 3  \n").unwrap().len(), 1);
+        assert_eq!(
+            parse_plan("# a plan\n\nstack 0 to dock 1").err(),
+            Some("line 3 : invalid movement : stack 0 to dock 1".to_string())
+        );
+        assert_eq!(
+            parse_plan("stack 0 to stack 1\nstack -1 to stack 1").err(),
+            Some("line 2 : invalid index : -1".to_string())
+        );
+        for text in ["stack 0 to stack 1", "stack 3 to place 8"].iter() {
+            assert_eq!(text.parse::<Movement>().unwrap().to_string(), *text);
+        }
+    }
+
+    /// The plans found by the a star are valid and load every container.
+    #[test]
+    fn test_astar_plan() {
+        let ship = Shippment::new_seeded(3, 1, 1);
+        let plan = AStar::new(ship.clone()).solve().unwrap();
+        let simulation = simulate(&ship, &plan).unwrap();
+        assert_eq!(simulation.snapshots.len(), plan.len() + 1);
+        assert!(simulation.complete());
+    }
+}
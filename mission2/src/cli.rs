@@ -0,0 +1,370 @@
+//! The command line interface : solves fleets given as arguments or in files.
+use std::fmt;
+use std::str::FromStr;
+
+use graphs::graphs::{AStar, StateNode};
+use harbours::{self, format_sol, simple_sol, Harbour, HarbourMove};
+use time::PreciseTime;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// The algorithms available to solve a fleet.
+pub enum Solver {
+    /// The a star, pruned by the greedy upper bound.
+    AStar,
+    /// The linearithmic closed form.
+    ClosedForm,
+    /// A bfs minimizing the number of moves, the time is not optimal.
+    Bfs,
+}
+
+impl FromStr for Solver {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "astar" => Ok(Solver::AStar),
+            "closed-form" => Ok(Solver::ClosedForm),
+            "bfs" => Ok(Solver::Bfs),
+            _ => Err(format!("unknown solver {}, expected astar, closed-form or bfs", s)),
+        }
+    }
+}
+
+impl fmt::Display for Solver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Solver::AStar => write!(f, "astar"),
+            Solver::ClosedForm => write!(f, "closed-form"),
+            Solver::Bfs => write!(f, "bfs"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// How the results are written.
+pub enum Format {
+    /// Readable text, a table in batch mode.
+    Text,
+    /// A json object, an array of objects in batch mode.
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {}, expected text or json", s)),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// Where the fleet to solve comes from.
+pub enum Input {
+    /// The speeds were given as arguments.
+    Fleet(Vec<i32>),
+    /// The speeds are in the given file.
+    File(String),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A command given to the program.
+pub enum Command {
+    /// Runs the examples of the report.
+    Demo,
+    /// Solves one fleet.
+    Solve(Input),
+    /// Solves every fleet of the file and writes the table in the output, or prints it if None.
+    Batch(String, Option<String>),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// The parsed command line.
+pub struct Options {
+    /// What to do.
+    pub command: Command,
+    /// The algorithm solving the fleets.
+    pub solver: Solver,
+    /// How the results are written.
+    pub format: Format,
+}
+
+/// The help printed when the arguments can not be parsed.
+pub const USAGE: &str = "usage :
+    mission2                                          runs the examples of the report
+    mission2 solve [options] <speed>...               solves the given fleet
+    mission2 solve [options] --file <fleet file>      solves the fleet in the file
+    mission2 batch [options] <fleets file> [<output>] solves one fleet per line of the file
+options :
+    --solver astar|closed-form|bfs                    the algorithm to use, astar by default
+    --format text|json                                how to write the results, text by default";
+
+/// Parses the arguments given to the program, without the name of the program.
+pub fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut solver = Solver::AStar;
+    let mut format = Format::Text;
+    let mut file = None;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--solver" | "--format" | "--file" => {
+                let value = args.next().ok_or(format!("missing value after {}", arg))?;
+                match arg.as_str() {
+                    "--solver" => solver = value.parse()?,
+                    "--format" => format = value.parse()?,
+                    _ => file = Some(value.clone()),
+                }
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let command = match (positional.first().map(|s| s.as_str()), file) {
+        (None, None) => Command::Demo,
+        (Some("solve"), Some(path)) if positional.len() == 1 => Command::Solve(Input::File(path)),
+        (Some("solve"), None) => Command::Solve(Input::Fleet(parse_fleet(&positional[1..].join(" "))?)),
+        (Some("batch"), None) if positional.len() == 2 || positional.len() == 3 => {
+            Command::Batch(positional[1].clone(), positional.get(2).cloned())
+        }
+        _ => return Err("invalid arguments".to_string()),
+    };
+    Ok(Options { command, solver, format })
+}
+
+/// The highest speed accepted for a boat.
+pub const MAX_SPEED: i32 = 10_000;
+
+/// The highest number of boats accepted in a fleet.
+pub const MAX_BOATS: usize = 10_000;
+
+/// Parses the speeds of a fleet, separated by spaces or commas.
+/// A fleet needs at least two boats, one boat can not bring the support team back.
+///
+/// Every boat takes part in at most three trips, so with at most *MAX_BOATS* boats of speed at
+/// most *MAX_SPEED* the times of the solvers and of the bounds fit in an i32.
+pub fn parse_fleet(text: &str) -> Result<Vec<i32>, String> {
+    let ships = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<i32>() {
+            Ok(speed) if speed > 0 && speed <= MAX_SPEED => Ok(speed),
+            Ok(speed) if speed > MAX_SPEED => Err(format!("the speed {} is above {}", s, MAX_SPEED)),
+            _ => Err(format!("invalid speed {}", s)),
+        })
+        .collect::<Result<Vec<i32>, String>>()?;
+    match ships.len() {
+        0 | 1 => Err("a fleet needs at least two boats".to_string()),
+        n if n > MAX_BOATS => Err(format!("a fleet has at most {} boats", MAX_BOATS)),
+        _ => Ok(ships),
+    }
+}
+
+/// Parses one fleet per line, blank lines and lines starting with # are ignored.
+/// Returns the number of the first invalid line with the error.
+pub fn parse_fleets(text: &str) -> Result<Vec<Vec<i32>>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|&(_, l)| !l.trim().is_empty() && !l.trim().starts_with('#'))
+        .map(|(i, l)| parse_fleet(l).map_err(|e| format!("line {} : {}", i + 1, e)))
+        .collect()
+}
+
+/// A harbour where every move costs one and without heuristic, so that the a star is a bfs.
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+struct Unweighted(Harbour);
+
+impl StateNode<HarbourMove> for Unweighted {
+    fn moves(&self) -> Vec<HarbourMove> {
+        self.0.moves()
+    }
+    fn modify(&mut self, mv: &HarbourMove) {
+        self.0.modify(mv)
+    }
+    fn end(&self) -> bool {
+        self.0.end()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+/// The result of a solver on a fleet.
+pub struct Report {
+    /// The speeds of the boats.
+    pub ships: Vec<i32>,
+    /// The algorithm used.
+    pub solver: Solver,
+    /// The time needed by the plan found.
+    pub time: i32,
+    /// The time taken by the solver in milliseconds.
+    pub elapsed_ms: f64,
+    /// The number of states expanded, None for the closed form.
+    pub expansions: Option<usize>,
+    /// The moves to make.
+    pub plan: Vec<HarbourMove>,
+}
+
+/// Solves the fleet with the given solver.
+pub fn solve(ships: &[i32], solver: Solver) -> Report {
+    let harbour = Harbour::new(ships.to_vec());
+    let start = PreciseTime::now();
+    let (plan, expansions) = match solver {
+        Solver::AStar => {
+            let mut astar = AStar::new(harbour.clone());
+            if let Some((upper, _)) = harbours::upper_bound(&harbour) {
+                astar.upper_bound(upper);
+            }
+            let plan = astar.solve();
+            (plan.unwrap(), Some(astar.expansions()))
+        }
+        Solver::ClosedForm => (simple_sol(ships).unwrap().1, None),
+        Solver::Bfs => {
+            let mut bfs = AStar::new(Unweighted(harbour.clone()));
+            let plan = bfs.solve();
+            (plan.unwrap(), Some(bfs.expansions()))
+        }
+    };
+    let end = PreciseTime::now();
+    Report {
+        ships: ships.to_vec(),
+        solver,
+        time: harbours::cost(&harbour, &plan),
+        elapsed_ms: start.to(end).num_microseconds().unwrap_or(0) as f64 / 1000.0,
+        expansions,
+        plan,
+    }
+}
+
+/// Writes a list of numbers as a json array.
+fn json_array(values: &[i32]) -> String {
+    format!("[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(", "))
+}
+
+impl Report {
+    /// Writes the report as readable text, the plan included.
+    pub fn to_text(&self) -> String {
+        let expansions = match self.expansions {
+            Some(e) => format!(", {} states expanded", e),
+            None => String::new(),
+        };
+        format!(
+            "the ships are : {:?}\nthe {} solution takes : {}\nfound in : {:.3} ms{}\n\
+             the solution found is :\n{}",
+            self.ships,
+            self.solver,
+            self.time,
+            self.elapsed_ms,
+            expansions,
+            format_sol(&Harbour::new(self.ships.clone()), &self.plan)
+        )
+    }
+
+    /// Writes the report as a json object, the plan included.
+    pub fn to_json(&self) -> String {
+        let plan = self.plan
+            .iter()
+            .map(|mv| match *mv {
+                HarbourMove::Cross(ref boats) => {
+                    let speeds = boats.iter().map(|b| b.speed).collect::<Vec<i32>>();
+                    format!("{{\"cross\": {}}}", json_array(&speeds))
+                },
+                HarbourMove::Return(boat) => format!("{{\"return\": {}}}", boat.speed),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!(
+            "{{\"ships\": {}, \"solver\": \"{}\", \"time\": {}, \"elapsed_ms\": {:.3}, \
+             \"expansions\": {}, \"plan\": [{}]}}",
+            json_array(&self.ships),
+            self.solver,
+            self.time,
+            self.elapsed_ms,
+            self.expansions.map_or("null".to_string(), |e| e.to_string()),
+            plan
+        )
+    }
+}
+
+/// Writes the reports of a batch as a table, one line per fleet, without the plans.
+pub fn table(reports: &[Report], format: Format) -> String {
+    match format {
+        Format::Text => {
+            let mut s = format!(
+                "{:<40} {:<12} {:>6} {:>12} {:>10}\n",
+                "ships",
+                "solver",
+                "time",
+                "elapsed_ms",
+                "expansions"
+            );
+            for r in reports {
+                s.push_str(&format!(
+                    "{:<40} {:<12} {:>6} {:>12.3} {:>10}\n",
+                    r.ships.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(","),
+                    r.solver.to_string(),
+                    r.time,
+                    r.elapsed_ms,
+                    r.expansions.map_or("-".to_string(), |e| e.to_string())
+                ));
+            }
+            s
+        }
+        Format::Json => format!(
+            "[\n{}\n]\n",
+            reports.iter().map(|r| r.to_json()).collect::<Vec<String>>().join(",\n")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]).unwrap().command, Command::Demo);
+        let options = parse_args(&args("solve --solver closed-form 3,6 15 24 --format json")).unwrap();
+        assert_eq!(options.command, Command::Solve(Input::Fleet(vec![3, 6, 15, 24])));
+        assert_eq!(options.solver, Solver::ClosedForm);
+        assert_eq!(options.format, Format::Json);
+        assert_eq!(
+            parse_args(&args("batch fleets.txt out.txt")).unwrap().command,
+            Command::Batch("fleets.txt".to_string(), Some("out.txt".to_string()))
+        );
+        assert!(parse_args(&args("solve 3")).is_err());
+        assert!(parse_args(&args("solve --solver dfs 3 6")).is_err());
+        assert!(parse_args(&args("batch")).is_err());
+    }
+
+    /// The speeds and the size of the fleet are bounded so that the times do not overflow.
+    #[test]
+    fn test_parse_fleet_limits() {
+        assert_eq!(parse_fleet("10000 1"), Ok(vec![MAX_SPEED, 1]));
+        assert!(parse_fleet("10001 1").is_err());
+        assert!(parse_fleet("2147483647 2147483647").is_err());
+        let largest = parse_fleet(&vec![MAX_SPEED.to_string(); MAX_BOATS].join(" ")).unwrap();
+        assert!(solve(&largest, Solver::ClosedForm).time > 0);
+        assert!(harbours::lower_bound(&Harbour::new(largest)) > 0);
+        assert!(parse_fleet(&vec!["1"; MAX_BOATS + 1].join(" ")).is_err());
+    }
+
+    #[test]
+    fn test_solvers_and_json() {
+        let fleets = parse_fleets("# course example\n3 6 15 24\n\n2, 2, 2\n").unwrap();
+        assert_eq!(fleets, vec![vec![3, 6, 15, 24], vec![2, 2, 2]]);
+        assert_eq!(solve(&fleets[0], Solver::AStar).time, 45);
+        assert_eq!(solve(&fleets[0], Solver::ClosedForm).time, 45);
+        assert!(solve(&fleets[0], Solver::Bfs).time >= 45);
+        let mut report = solve(&[1, 2], Solver::ClosedForm);
+        report.elapsed_ms = 0.0;
+        assert_eq!(
+            report.to_json(),
+            "{\"ships\": [1, 2], \"solver\": \"closed-form\", \"time\": 2, \"elapsed_ms\": 0.000, \
+             \"expansions\": null, \"plan\": [{\"cross\": [1, 2]}]}"
+        );
+        assert!(parse_fleets("3 6\nfast 2\n").unwrap_err().starts_with("line 2"));
+    }
+}
@@ -0,0 +1,238 @@
+//! The ferry : the harbour problem with other capacities and costs for the return trips.
+use graphs::graphs::StateNode;
+use itertools::Itertools;
+use util::remove_at;
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, Debug)]
+/// How the duration of a return trip is computed.
+pub enum ReturnCost {
+    /// The return takes the time of the boat bringing back the support team.
+    Speed,
+    /// Every return takes the same time, whatever the boat.
+    Fixed(i32),
+}
+
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// A move of the generalized problem.
+/// Some boats cross together, then one boat brings the support team back if needed.
+pub struct FerryMove {
+    /// The boats crossing together.
+    pub crossing: Vec<i32>,
+    /// The boat bringing back the support team, None if every boat arrived.
+    pub returner: Option<i32>,
+}
+
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// A state of the generalized problem.
+/// Up to *capacity* boats can cross together and only some boats may be allowed to bring the
+/// support team back.
+pub struct Ferry {
+    begining: Vec<i32>,
+    arival: Vec<i32>,
+    /// The maximum number of boats crossing together.
+    capacity: usize,
+    /// The boats allowed to bring the support team back, None if every boat can.
+    returners: Option<Vec<i32>>,
+    /// How the duration of a return is computed.
+    return_cost: ReturnCost,
+}
+
+impl Ferry {
+    /// Creates a new instance of the problem with the given boats at the beggining.
+    /// By default two boats cross together and any boat can come back, at its own speed.
+    pub fn new(mut ships: Vec<i32>) -> Self {
+        ships.sort();
+        Ferry {
+            begining: ships,
+            arival: vec![],
+            capacity: 2,
+            returners: None,
+            return_cost: ReturnCost::Speed,
+        }
+    }
+    /// Sets the maximum number of boats crossing together.
+    pub fn capacity(&mut self, capacity: usize) -> &mut Self {
+        assert!(capacity > 0, "at least one boat must be able to cross");
+        self.capacity = capacity;
+        self
+    }
+    /// Only allows the boats with the given speeds to bring the support team back.
+    pub fn returners(&mut self, returners: Vec<i32>) -> &mut Self {
+        self.returners = Some(returners);
+        self
+    }
+    /// Sets how the duration of a return is computed.
+    pub fn return_cost(&mut self, return_cost: ReturnCost) -> &mut Self {
+        self.return_cost = return_cost;
+        self
+    }
+    /// Returns true if the boat with the given speed may bring the support team back.
+    fn can_return(&self, boat: i32) -> bool {
+        match self.returners {
+            Some(ref r) => r.contains(&boat),
+            None => true,
+        }
+    }
+    /// Returns the duration of a return made by the given boat.
+    fn return_time(&self, boat: i32) -> i32 {
+        match self.return_cost {
+            ReturnCost::Speed => boat,
+            ReturnCost::Fixed(t) => t,
+        }
+    }
+}
+
+impl StateNode<FerryMove> for Ferry {
+    /// Every group of at most *capacity* boats can cross.
+    /// If boats remain at the beggining, every allowed boat at the end can then come back.
+    fn moves(&self) -> Vec<FerryMove> {
+        let mut groups = (1..self.capacity.min(self.begining.len()) + 1)
+            .flat_map(|k| {
+                self.begining
+                    .iter()
+                    .cloned()
+                    .combinations(k)
+                    .map(|mut g| {
+                        g.sort();
+                        g
+                    })
+                    .collect::<Vec<Vec<i32>>>()
+            })
+            .collect::<Vec<Vec<i32>>>();
+        groups.sort();
+        groups.dedup();
+        let mut mvs = vec![];
+        for crossing in groups {
+            if crossing.len() == self.begining.len() {
+                mvs.push(FerryMove { crossing, returner: None });
+                continue;
+            }
+            let mut candidates = self.arival
+                .iter()
+                .chain(crossing.iter())
+                .cloned()
+                .filter(|b| self.can_return(*b))
+                .collect::<Vec<i32>>();
+            candidates.sort();
+            candidates.dedup();
+            for returner in candidates {
+                mvs.push(FerryMove {
+                    crossing: crossing.clone(),
+                    returner: Some(returner),
+                });
+            }
+        }
+        mvs
+    }
+    /// Moves the crossing boats to the end, then brings the returner back.
+    /// The boats are kept sorted so that the same boats on each side give the same state.
+    fn modify(&mut self, mv: &FerryMove) {
+        for boat in mv.crossing.iter() {
+            remove_at(&mut self.begining, boat);
+            self.arival.push(*boat);
+        }
+        if let Some(returner) = mv.returner {
+            remove_at(&mut self.arival, &returner);
+            self.begining.push(returner);
+        }
+        self.begining.sort();
+        self.arival.sort();
+    }
+    /// If the beggining is empty, we found a solution.
+    fn end(&self) -> bool {
+        self.begining.is_empty()
+    }
+    /// The crossing takes the time of the slowest boat, plus the time of the return.
+    fn cost_to(&self, _target: &Self, mv: &FerryMove) -> i32 {
+        mv.crossing.iter().max().unwrap() + mv.returner.map_or(0, |r| self.return_time(r))
+    }
+    /// Ignoring the returns, the boats can at best cross by groups of *capacity* boats of
+    /// similar speeds : the slowest boat, then the *capacity*-th slowest and so on.
+    fn dist_from_end(&self) -> i32 {
+        let mut boats = self.begining.clone();
+        boats.sort_by(|a, b| b.cmp(a));
+        boats.iter().step_by(self.capacity).sum()
+    }
+}
+
+/// Returns the total time of the solution, starting from the given state.
+pub fn cost(start: &Ferry, solution: &[FerryMove]) -> i32 {
+    let mut state = start.clone();
+    let mut total = 0;
+    for mv in solution {
+        let mut next = state.clone();
+        next.modify(mv);
+        total += state.cost_to(&next, mv);
+        state = next;
+    }
+    total
+}
+
+/// Pretty Prints the solution
+pub fn print_sol(solution: Vec<FerryMove>) {
+    for mv in solution {
+        println!("brings the boats of size : {:?} to the end", mv.crossing);
+        if let Some(r) = mv.returner {
+            println!("bring back the support team on the boat of size : {}", r);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphs::graphs::AStar;
+    use harbours::simple_sol;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    /// Returns the optimal time of the ferry, checking that its moves are allowed.
+    fn optimal(ferry: &Ferry) -> i32 {
+        let sol = AStar::new(ferry.clone()).solve().unwrap();
+        let mut state = ferry.clone();
+        for mv in sol.iter() {
+            assert!(mv.crossing.len() <= ferry.capacity, "{:?} is too large", mv);
+            assert!(mv.returner.is_none_or(|r| ferry.can_return(r)), "{:?} can not return", mv);
+            assert!(state.moves().contains(mv), "invalid move {:?}", mv);
+            state.modify(mv);
+        }
+        assert!(state.end());
+        cost(ferry, &sol)
+    }
+
+    /// With two boats per crossing and returns at the speed of the boat, the ferry is the
+    /// harbour problem and takes the time of the closed form.
+    #[test]
+    fn test_same_as_harbour() {
+        let mut my_rand = XorShiftRng::from_seed([5, 7, 11, 13]);
+        assert_eq!(optimal(&Ferry::new(vec![3, 6, 15, 24])), 45);
+        for nb_ships in 2..7 {
+            let ships = (0..nb_ships)
+                .map(|_| my_rand.gen_range(1, 30))
+                .collect::<Vec<i32>>();
+            assert_eq!(optimal(&Ferry::new(ships.clone())), simple_sol(&ships).unwrap().0, "for {:?}", ships);
+        }
+    }
+
+    /// Three boats crossing together save a return, four boats cross at once.
+    #[test]
+    fn test_capacity() {
+        let mut ferry = Ferry::new(vec![3, 6, 15, 24]);
+        assert_eq!(optimal(ferry.capacity(3)), 33);
+        assert_eq!(optimal(ferry.capacity(4)), 24);
+    }
+
+    /// Only the slow boats may bring the support team back.
+    #[test]
+    fn test_returners() {
+        let mut ferry = Ferry::new(vec![3, 6, 15, 24]);
+        assert_eq!(optimal(ferry.returners(vec![15, 24])), 84);
+    }
+
+    /// Every return takes the same time, whatever the boat.
+    #[test]
+    fn test_fixed_return() {
+        let mut ferry = Ferry::new(vec![3, 6, 15, 24]);
+        assert_eq!(optimal(ferry.return_cost(ReturnCost::Fixed(1))), 38);
+        assert_eq!(optimal(ferry.return_cost(ReturnCost::Fixed(0))), 24 + 6 + 6);
+    }
+}
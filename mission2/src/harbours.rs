@@ -1,50 +1,224 @@
+//! The harbour problem : boats cross two by two and one of them brings the support team back.
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fmt::Debug;
-use graphs::graphs::StateNode;
-use itertools::Itertools;
+use graphs::graphs::{AStar, StateNode};
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// A boat, ordered by speed first so that the fastest boat of a fleet comes first.
+pub struct Boat {
+    /// The time the boat takes to cross.
+    pub speed: i32,
+    /// The number of trips the boat can still make, None if its fuel is unlimited.
+    /// Both the crossings and the returns consume a trip.
+    pub fuel: Option<u32>,
+}
+
+impl Boat {
+    /// Creates a boat with unlimited fuel.
+    pub fn new(speed: i32) -> Self {
+        Boat { speed, fuel: None }
+    }
+    /// Creates a boat able to make the given number of trips.
+    pub fn with_fuel(speed: i32, fuel: u32) -> Self {
+        Boat { speed, fuel: Some(fuel) }
+    }
+    /// Returns true if the boat has enough fuel for one more trip.
+    pub fn can_travel(&self) -> bool {
+        self.fuel != Some(0)
+    }
+    /// Returns the boat after a trip.
+    fn travel(self) -> Self {
+        Boat {
+            speed: self.speed,
+            fuel: self.fuel.map(|f| f - 1),
+        }
+    }
+}
+
+impl fmt::Display for Boat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.fuel {
+            Some(fuel) => write!(f, "{} ({} trips left)", self.speed, fuel),
+            None => write!(f, "{}", self.speed),
+        }
+    }
+}
+
+#[derive(Hash, Clone, PartialEq, Eq, Debug)]
+/// A move of the problem.
+pub enum HarbourMove {
+    /// The given boats cross together with the support team.
+    Cross(Vec<Boat>),
+    /// The given boat brings the support team back to the beggining.
+    Return(Boat),
+}
+
+/// A multiset of boats : associates each boat to the number of identical boats.
+pub type Fleet = BTreeMap<Boat, usize>;
+
+/// Adds a boat to the fleet.
+pub fn add_boat(fleet: &mut Fleet, boat: Boat) {
+    *fleet.entry(boat).or_insert(0) += 1;
+}
+
+/// Removes a boat from the fleet.
+pub fn remove_boat(fleet: &mut Fleet, boat: Boat) {
+    let remaining = {
+        let count = fleet.get_mut(&boat).unwrap();
+        *count -= 1;
+        *count
+    };
+    if remaining == 0 {
+        fleet.remove(&boat);
+    }
+}
+
+/// Returns the number of boats in the fleet.
+fn fleet_size(fleet: &Fleet) -> usize {
+    fleet.values().sum()
+}
 
 #[derive(Hash, Clone, PartialEq, Eq, Debug)]
 /// A state of the problem.
-/// contains boat at the beggining and the end.
-/// if there are boats at the beggining, then the support team is at the beggining.
+/// contains boat at the beggining and the end, and the side of the support team.
+/// Boats with the same speed and fuel are interchangeable so only their number is kept.
+///
+/// The trips can be restricted to opening windows and the whole crossing to a deadline.
+/// The clock is only kept when the windows or the deadline depend on it, so that states of the
+/// unconstrained problem reached at different times are the same.
 pub struct Harbour {
-    begining: Vec<i32>,
-    arival: Vec<i32>,
+    begining: Fleet,
+    arival: Fleet,
+    /// True if the support team is at the end and must be brought back.
+    team_arrived: bool,
+    /// The time at which the last trip ended.
+    clock: i32,
+    /// The (opening, closing) times of the harbour, a trip must start and end inside one of
+    /// them. Empty if the harbour is always open.
+    windows: Vec<(i32, i32)>,
+    /// The time at which every boat must have arrived, None if there is no deadline.
+    deadline: Option<i32>,
 }
 
 impl Harbour {
     /// creates a new instance of the problem with the given boats at the beggining.
     pub fn new(ships: Vec<i32>) -> Self {
+        Harbour::new_with_fuel(ships.into_iter().map(Boat::new).collect())
+    }
+    /// creates a new instance of the problem with the given boats, each with its own fuel.
+    pub fn new_with_fuel(boats: Vec<Boat>) -> Self {
+        let mut begining = Fleet::new();
+        for boat in boats {
+            add_boat(&mut begining, boat);
+        }
         Harbour {
-            begining: ships,
-            arival: vec![],
+            begining,
+            arival: Fleet::new(),
+            team_arrived: false,
+            clock: 0,
+            windows: vec![],
+            deadline: None,
+        }
+    }
+    /// Only allows the trips starting and ending inside one of the (opening, closing) windows.
+    pub fn windows(&mut self, windows: Vec<(i32, i32)>) -> &mut Self {
+        self.windows = windows;
+        self
+    }
+    /// Requires every boat to have arrived at the given time.
+    pub fn deadline(&mut self, deadline: i32) -> &mut Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    /// Returns the number of boats at the beggining.
+    fn nb_begining(&self) -> usize {
+        fleet_size(&self.begining)
+    }
+    /// Returns true if the clock must be kept.
+    fn timed(&self) -> bool {
+        !self.windows.is_empty() || self.deadline.is_some()
+    }
+    /// Returns the earliest time at which a trip of the given duration can start.
+    /// Returns None if no window is long enough anymore.
+    fn departure(&self, duration: i32) -> Option<i32> {
+        if self.windows.is_empty() {
+            return Some(self.clock);
+        }
+        self.windows
+            .iter()
+            .map(|&(open, close)| (open.max(self.clock), close))
+            .filter(|&(start, close)| start + duration <= close)
+            .map(|(start, _)| start)
+            .min()
+    }
+    /// Returns true if the move fits in a window and can still end before the deadline.
+    fn allows(&self, mv: &HarbourMove) -> bool {
+        let arrival = match self.departure(move_time(mv)) {
+            Some(start) => start + move_time(mv),
+            None => return false,
+        };
+        match self.deadline {
+            Some(deadline) => {
+                let mut next = self.clone();
+                next.modify(mv);
+                arrival + lower_bound(&next) <= deadline
+            }
+            None => true,
         }
     }
 }
 
-/// Removes an element from a vector.
-pub fn remove_at<U: Eq + Debug>(vec: &mut Vec<U>, elem: &U) {
-    let to_rem = vec.iter().position(|s| s == elem).unwrap();
-    vec.remove(to_rem);
-}
-
-impl StateNode<(i32, i32)> for Harbour {
-    /// The moves here are just sending two boats to the end
-    /// the fastest boat at the end will return.
-    fn moves(&self) -> Vec<(i32, i32)> {
-        self.begining.iter().combinations(2).map(|v| (*v[0], *v[1])).collect()
+impl StateNode<HarbourMove> for Harbour {
+    /// If the support team is at the beggining, two boats of any two kinds can cross together.
+    /// Else a boat of any kind at the end can bring it back.
+    /// The boats need fuel for the trip, which must respect the windows and the deadline.
+    fn moves(&self) -> Vec<HarbourMove> {
+        let mvs = match self.team_arrived {
+            false => {
+                let boats = self.begining
+                    .iter()
+                    .filter(|&(b, _)| b.can_travel())
+                    .collect::<Vec<(&Boat, &usize)>>();
+                let mut mvs = vec![];
+                for (i, &(&a, &count)) in boats.iter().enumerate() {
+                    if count > 1 {
+                        mvs.push(HarbourMove::Cross(vec![a, a]));
+                    }
+                    for &(&b, _) in boats.iter().skip(i + 1) {
+                        mvs.push(HarbourMove::Cross(vec![a, b]));
+                    }
+                }
+                mvs
+            }
+            true => self.arival
+                .keys()
+                .filter(|b| b.can_travel())
+                .cloned()
+                .map(HarbourMove::Return)
+                .collect(),
+        };
+        mvs.into_iter().filter(|mv| self.allows(mv)).collect()
     }
     /// Takes a move and modify the game with it.
-    /// Removes the boats from the beginning and put them at the end.
-    /// Then if there are still boats at the beggining, brings the fastest ship at the end back.
-    fn modify(&mut self, mv: &(i32, i32)) {
-        remove_at(&mut self.begining, &mv.0);
-        remove_at(&mut self.begining, &mv.1);
-        self.arival.push(mv.0);
-        self.arival.push(mv.1);
-        if !self.end() {
-            let fastest = self.arival.iter().min().unwrap().clone();
-            remove_at(&mut self.arival, &fastest);
-            self.begining.push(fastest);
+    fn modify(&mut self, mv: &HarbourMove) {
+        if self.timed() {
+            let duration = move_time(mv);
+            self.clock = self.departure(duration).unwrap_or(self.clock) + duration;
+        }
+        match *mv {
+            HarbourMove::Cross(ref boats) => {
+                for boat in boats {
+                    remove_boat(&mut self.begining, *boat);
+                    add_boat(&mut self.arival, boat.travel());
+                }
+                self.team_arrived = true;
+            }
+            HarbourMove::Return(boat) => {
+                remove_boat(&mut self.arival, boat);
+                add_boat(&mut self.begining, boat.travel());
+                self.team_arrived = false;
+            }
         }
     }
     /// If the beggining is empty, we found a solution.
@@ -53,25 +227,474 @@ impl StateNode<(i32, i32)> for Harbour {
     }
 
     /// Calculates the distance bewteen the current state and the target state.
-    fn cost_to(&self, _target: &Self, mv: &(i32, i32)) -> i32 {
-        let maxs = vec![mv.0, mv.1, self.arival.iter().min().unwrap_or(&100).clone()];
-        let min_ret = match self.begining.len()==2 {
-            true => maxs.iter().min().unwrap(),
-            false => &0,
-        };
-        [mv.0, mv.1].iter().max().unwrap() + min_ret
+    /// The boats cross at the speed of the slowest one, a boat returns at its own speed.
+    /// If the harbour is closed, the boats wait for the next window.
+    fn cost_to(&self, _target: &Self, mv: &HarbourMove) -> i32 {
+        let duration = move_time(mv);
+        self.departure(duration).unwrap_or(self.clock) - self.clock + duration
     }
 
-    /// Proven to respect monotony.
+    /// Returns the certified lower bound of the remaining time, see *lower_bound*.
     fn dist_from_end(&self) -> i32 {
-        ((self.begining.iter().sum::<i32>() as i32) - (self.begining.iter().min().unwrap()))/2
+        lower_bound(self)
+    }
+}
+
+/// Returns a lower bound of the time needed to bring every boat to the end from this state.
+///
+/// Proof : with n boats at the beggining and the support team with them, a crossing brings at
+/// most two boats to the end and a return brings one back, so at least n - 1 crossings and
+/// n - 2 returns are needed. Every boat crosses at least once and at most two boats cross
+/// together, so ordering the crossings by duration, the k-th slowest takes at least the
+/// (2k - 1)-th slowest boat. Every other trip takes at least the fastest boat. If the support
+/// team is at the end, one of the boats at the end must first bring it back.
+/// The fuel, the windows and the deadline only remove solutions so the bound still holds.
+///
+/// A move of duration d lowers the bound by at most d, so the a star using it stays optimal.
+pub fn lower_bound(harbour: &Harbour) -> i32 {
+    if harbour.begining.is_empty() {
+        return 0;
+    }
+    let fastest = harbour.begining.keys().chain(harbour.arival.keys()).map(|b| b.speed).min().unwrap();
+    let (first_return, n) = match harbour.team_arrived {
+        true => (harbour.arival.keys().next().unwrap().speed, harbour.nb_begining() + 1),
+        false => (0, harbour.nb_begining()),
+    };
+    let slowest_crossings = harbour
+        .begining
+        .iter()
+        .rev()
+        .flat_map(|(b, &c)| ::std::iter::repeat_n(b.speed, c))
+        .step_by(2)
+        .sum::<i32>();
+    let other_crossings = (n as i32 - 1 - (n as i32 + 1) / 2).max(0);
+    let returns = (n as i32 - 2).max(0);
+    first_return + slowest_crossings + (other_crossings + returns) * fastest
+}
+
+/// Returns the boat of the given speed with the most fuel left in the fleet, if it can travel.
+fn pick_boat(fleet: &Fleet, speed: i32) -> Option<Boat> {
+    fleet
+        .keys()
+        .filter(|b| b.speed == speed && b.can_travel())
+        .max_by_key(|b| b.fuel.unwrap_or(u32::MAX))
+        .cloned()
+}
+
+/// Returns the time and the moves of a greedy solution from this state, an upper bound of the
+/// optimal time.
+/// If the support team is at the end, the fastest boat there brings it back, then the boats at
+/// the beggining follow the closed form. Each move of the closed form is made by the boats of
+/// the fleet with the right speeds and the most fuel left.
+///
+/// Returns None if the greedy solution breaks the fuel, windows or deadline constraints.
+pub fn upper_bound(harbour: &Harbour) -> Option<(i32, Vec<HarbourMove>)> {
+    let mut sol = vec![];
+    if harbour.end() {
+        return Some((0, sol));
+    }
+    let mut ships = harbour
+        .begining
+        .iter()
+        .flat_map(|(b, &c)| ::std::iter::repeat_n(b.speed, c))
+        .collect::<Vec<i32>>();
+    if harbour.team_arrived {
+        let fastest = harbour.arival.keys().next().unwrap().speed;
+        sol.push(HarbourMove::Return(Boat::new(fastest)));
+        ships.push(fastest);
+    }
+    sol.extend(simple_sol(&ships)?.1);
+    let mut state = harbour.clone();
+    let mut plan = vec![];
+    for mv in sol {
+        let mv = match mv {
+            HarbourMove::Cross(boats) => {
+                let mut fleet = state.begining.clone();
+                let mut picked = vec![];
+                for boat in boats {
+                    let boat = pick_boat(&fleet, boat.speed)?;
+                    remove_boat(&mut fleet, boat);
+                    picked.push(boat);
+                }
+                picked.sort();
+                HarbourMove::Cross(picked)
+            }
+            HarbourMove::Return(boat) => HarbourMove::Return(pick_boat(&state.arival, boat.speed)?),
+        };
+        if !state.moves().contains(&mv) {
+            return None;
+        }
+        state.modify(&mv);
+        plan.push(mv);
+    }
+    Some((cost(harbour, &plan), plan))
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// The reason why no schedule can bring every boat to the end.
+pub enum Infeasibility {
+    /// Only one boat is at the beggining and it can not bring the support team back.
+    SingleBoat,
+    /// The boat at the beggining has no fuel left and will never cross.
+    Stranded(Boat),
+    /// The boats need at least *needed* trips but their fuel only allows *available* trips.
+    NotEnoughFuel {
+        /// The minimum number of trips.
+        needed: u32,
+        /// The number of trips allowed by the fuel of the boats.
+        available: u32,
+    },
+    /// No window is long enough for the boat to cross.
+    NoWindow(Boat),
+    /// Even the lower bound of the crossing ends after the deadline.
+    Deadline {
+        /// The earliest time at which every boat could have arrived.
+        lower_bound: i32,
+        /// The deadline.
+        deadline: i32,
+    },
+    /// The instance passes the checks above but the search found no schedule.
+    NoSchedule,
+}
+
+impl fmt::Display for Infeasibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Infeasibility::SingleBoat => write!(f, "one boat can not bring the support team back"),
+            Infeasibility::Stranded(b) => write!(f, "the boat {} has no fuel left", b),
+            Infeasibility::NotEnoughFuel { needed, available } => write!(
+                f,
+                "at least {} trips are needed but the fuel only allows {}",
+                needed,
+                available
+            ),
+            Infeasibility::NoWindow(b) => write!(f, "no window is long enough for the boat {}", b),
+            Infeasibility::Deadline { lower_bound, deadline } => write!(
+                f,
+                "the boats can not arrive before {}, after the deadline {}",
+                lower_bound,
+                deadline
+            ),
+            Infeasibility::NoSchedule => write!(f, "no schedule satisfies the constraints"),
+        }
+    }
+}
+
+/// Returns the number of crossings and of returns needed to bring n boats and the support team
+/// with them to the end, two boats crossing together.
+/// Each crossing but the last one is followed by a return, and each crossing brings one more
+/// boat to the end : n - 1 crossings and n - 2 returns. Returns None for a single boat.
+pub fn trips(n: usize) -> Option<(usize, usize)> {
+    match n {
+        0 => Some((0, 0)),
+        1 => None,
+        _ => Some((n - 1, n - 2)),
+    }
+}
+
+/// Checks the conditions every schedule must satisfy, without searching.
+///
+/// The boats need the trips counted by `trips`, a crossing taking the fuel of two boats, plus the
+/// return bringing the support team back if it is at the end. Every boat at the beggining must
+/// cross once, within a window.
+pub fn check_feasibility(harbour: &Harbour) -> Result<(), Infeasibility> {
+    if harbour.end() {
+        return Ok(());
+    }
+    let n = match harbour.team_arrived {
+        true => harbour.nb_begining() + 1,
+        false => harbour.nb_begining(),
+    };
+    let (crossings, returns) = trips(n).ok_or(Infeasibility::SingleBoat)?;
+    if let Some(&b) = harbour.begining.keys().find(|b| !b.can_travel()) {
+        return Err(Infeasibility::Stranded(b));
+    }
+    let needed = (2 * crossings + returns) as u32 + harbour.team_arrived as u32;
+    let boats = harbour.begining.iter().chain(harbour.arival.iter());
+    if boats.clone().all(|(b, _)| b.fuel.is_some()) {
+        let available = boats.map(|(b, &c)| b.fuel.unwrap() * c as u32).sum::<u32>();
+        if available < needed {
+            return Err(Infeasibility::NotEnoughFuel { needed, available });
+        }
+    }
+    if let Some(&b) = harbour.begining.keys().find(|b| harbour.departure(b.speed).is_none()) {
+        return Err(Infeasibility::NoWindow(b));
+    }
+    if let Some(deadline) = harbour.deadline {
+        let lower_bound = harbour.clock + lower_bound(harbour);
+        if lower_bound > deadline {
+            return Err(Infeasibility::Deadline { lower_bound, deadline });
+        }
+    }
+    Ok(())
+}
+
+/// Finds the fastest schedule bringing every boat to the end with the a star, pruned by the
+/// greedy upper bound when it respects the constraints.
+/// Returns why there is no schedule otherwise.
+pub fn schedule(harbour: &Harbour) -> Result<Vec<HarbourMove>, Infeasibility> {
+    check_feasibility(harbour)?;
+    let mut astar = AStar::new(harbour.clone());
+    if let Some((upper, _)) = upper_bound(harbour) {
+        astar.upper_bound(upper);
+    }
+    astar.solve().ok_or(Infeasibility::NoSchedule)
+}
+
+/// Returns the time taken by a move.
+pub fn move_time(mv: &HarbourMove) -> i32 {
+    match *mv {
+        HarbourMove::Cross(ref boats) => boats.iter().map(|b| b.speed).max().unwrap(),
+        HarbourMove::Return(boat) => boat.speed,
+    }
+}
+
+/// This is the solution in linearithmic time explained at the end of the report.
+/// Returns the time needed and the moves to make.
+///
+/// While more than three boats remain, the two slowest ones (y, z) are brought to the end
+/// using the two fastest ones (a, b) in the cheapest of two ways :
+/// - a and b cross, a comes back, y and z cross, b comes back : a + 2b + z.
+/// - a and z cross, a comes back, a and y cross, a comes back : 2a + y + z.
+///
+/// Then three boats a, b, c take a + b + c and two boats take b.
+/// It makes the trips counted by `trips`, so returns None for a single boat.
+pub fn simple_sol(ships: &[i32]) -> Option<(i32, Vec<HarbourMove>)> {
+    trips(ships.len())?;
+    let cross = |a, b| HarbourMove::Cross(vec![Boat::new(a), Boat::new(b)]);
+    let back = |a| HarbourMove::Return(Boat::new(a));
+    let mut s_ships = ships.to_vec();
+    s_ships.sort();
+    let mut sol = vec![];
+    while s_ships.len() > 3 {
+        let (a, b) = (s_ships[0], s_ships[1]);
+        let z = s_ships.pop().unwrap();
+        let y = s_ships.pop().unwrap();
+        if a + 2 * b + z < 2 * a + y + z {
+            sol.extend(vec![cross(a, b), back(a), cross(y, z), back(b)]);
+        } else {
+            sol.extend(vec![cross(a, z), back(a), cross(a, y), back(a)]);
+        }
+    }
+    match s_ships.len() {
+        3 => {
+            sol.extend(vec![
+                cross(s_ships[0], s_ships[2]),
+                back(s_ships[0]),
+                cross(s_ships[0], s_ships[1]),
+            ])
+        }
+        2 => sol.push(cross(s_ships[0], s_ships[1])),
+        _ => (),
+    }
+    Some((sol.iter().map(move_time).sum(), sol))
+}
+
+/// Returns the time needed by the solution, starting from the given state.
+pub fn cost(start: &Harbour, solution: &[HarbourMove]) -> i32 {
+    let mut state = start.clone();
+    let mut total = 0;
+    for mv in solution {
+        let mut next = state.clone();
+        next.modify(mv);
+        total += state.cost_to(&next, mv);
+        state = next;
     }
+    total
 }
 
-/// Pretty Prints the solution
-pub fn print_sol(solution : Vec<(i32, i32)>) {
+/// Writes the solution played from the given state, one move per line.
+/// Each move is preceded by the time at wich it ends.
+pub fn format_sol(start: &Harbour, solution: &[HarbourMove]) -> String {
+    let mut state = start.clone();
+    let mut clock = 0;
+    let mut s = String::new();
     for mv in solution {
-        println!("brings the two boats of size : {}, {} to the end", mv.0, mv.1);
-        println!("bring back the support team on the fastest possible boat.");
+        let mut next = state.clone();
+        next.modify(mv);
+        clock += state.cost_to(&next, mv);
+        state = next;
+        match *mv {
+            HarbourMove::Cross(ref boats) => s.push_str(&format!(
+                "[{:>4}] brings the boats of size : [{}] to the end\n",
+                clock,
+                boats.iter().map(|b| b.to_string()).collect::<Vec<String>>().join(", ")
+            )),
+            HarbourMove::Return(boat) => s.push_str(&format!(
+                "[{:>4}] bring back the support team on the boat of size : {}\n",
+                clock,
+                boat
+            )),
+        }
+    }
+    s
+}
+
+/// Pretty Prints the solution played from the given state.
+/// Each move is preceded by the time at wich it ends.
+pub fn print_sol(start: &Harbour, solution : Vec<HarbourMove>) {
+    print!("{}", format_sol(start, &solution));
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphs::graphs::AStar;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    /// The course example takes 45 : 3 and 6 cross, 3 comes back, 15 and 24 cross, 6 comes back,
+    /// 3 and 6 cross.
+    #[test]
+    fn test_course_example() {
+        let cross = |a, b| HarbourMove::Cross(vec![Boat::new(a), Boat::new(b)]);
+        let back = |a| HarbourMove::Return(Boat::new(a));
+        let (time, sol) = simple_sol(&[3, 6, 15, 24]).unwrap();
+        assert_eq!(time, 45);
+        assert_eq!(sol, vec![cross(3, 6), back(3), cross(15, 24), back(6), cross(3, 6)]);
+        let harbour = Harbour::new(vec![3, 6, 15, 24]);
+        assert_eq!(cost(&harbour, &AStar::new(harbour.clone()).solve().unwrap()), 45);
+    }
+
+    /// A single boat can not bring the support team back and an empty fleet has nothing to do,
+    /// as for the a star.
+    #[test]
+    fn test_simple_sol_small_fleets() {
+        let harbour = Harbour::new(vec![7]);
+        assert_eq!(simple_sol(&[7]), None);
+        assert_eq!(AStar::new(harbour.clone()).solve(), None);
+        assert_eq!(check_feasibility(&harbour), Err(Infeasibility::SingleBoat));
+        assert_eq!(upper_bound(&harbour), None);
+        let harbour = Harbour::new(vec![]);
+        assert_eq!(simple_sol(&[]), Some((0, vec![])));
+        assert_eq!(AStar::new(harbour.clone()).solve(), Some(vec![]));
+    }
+
+    /// Compares the closed form with the a star on random fleets of 2 to 12 boats.
+    /// The moves of the closed form must be valid and take the announced time.
+    #[test]
+    fn test_simple_sol_against_astar() {
+        let mut my_rand = XorShiftRng::from_seed([7, 11, 13, 17]);
+        for nb_ships in 2..13 {
+            for _ in 0..3 {
+                let ships = (0..nb_ships)
+                    .map(|_| my_rand.gen_range(1, 30))
+                    .collect::<Vec<i32>>();
+                let harbour = Harbour::new(ships.clone());
+                let (time, sol) = simple_sol(&ships).unwrap();
+                let mut state = harbour.clone();
+                for mv in sol.iter() {
+                    assert!(state.moves().contains(mv), "invalid move {:?} for {:?}", mv, ships);
+                    state.modify(mv);
+                }
+                assert!(state.end());
+                assert_eq!(cost(&harbour, &sol), time);
+                let crossings = sol.iter().filter(|mv| match **mv {
+                    HarbourMove::Cross(ref boats) => boats.len() == 2,
+                    HarbourMove::Return(_) => false,
+                }).count();
+                assert_eq!(trips(nb_ships), Some((crossings, sol.len() - crossings)));
+                let astar = AStar::new(harbour.clone()).solve().unwrap();
+                assert_eq!(cost(&harbour, &astar), time, "for the ships {:?}", ships);
+            }
+        }
+    }
+
+    /// From random states of random fleets, the optimal time must be between the bounds.
+    /// The a star pruned by the upper bound must find the same time.
+    #[test]
+    fn test_bounds() {
+        let mut my_rand = XorShiftRng::from_seed([3, 5, 8, 13]);
+        for nb_ships in 2..10 {
+            let ships = (0..nb_ships)
+                .map(|_| my_rand.gen_range(1, 30))
+                .collect::<Vec<i32>>();
+            let mut harbour = Harbour::new(ships.clone());
+            while !harbour.end() {
+                let optimal = cost(&harbour, &AStar::new(harbour.clone()).solve().unwrap());
+                let (upper, sol) = upper_bound(&harbour).unwrap();
+                assert_eq!(cost(&harbour, &sol), upper);
+                assert!(lower_bound(&harbour) <= optimal, "for {:?}", harbour);
+                assert!(optimal <= upper, "for {:?}", harbour);
+                let pruned = AStar::new(harbour.clone()).upper_bound(upper).solve().unwrap();
+                assert_eq!(cost(&harbour, &pruned), optimal);
+                let moves = harbour.moves();
+                let mv = moves[my_rand.gen_range(0, moves.len())].clone();
+                harbour.modify(&mv);
+            }
+        }
+    }
+
+    /// Boats with the same speed are interchangeable : 40 boats with 10 speeds give few states.
+    #[test]
+    fn test_duplicated_speeds() {
+        let ships = (0..40).map(|i| i % 10 + 1).collect::<Vec<i32>>();
+        let harbour = Harbour::new(ships.clone());
+        assert_eq!(harbour.moves().len(), 55);
+        let astar = AStar::new(harbour.clone()).solve().unwrap();
+        assert_eq!(cost(&harbour, &astar), simple_sol(&ships).unwrap().0);
+    }
+
+    /// The boats can not make more trips than their fuel allows.
+    #[test]
+    fn test_fuel() {
+        let course = |fuels: [u32; 4]| {
+            Harbour::new_with_fuel(
+                [3, 6, 15, 24].iter().zip(fuels.iter()).map(|(&s, &f)| Boat::with_fuel(s, f)).collect(),
+            )
+        };
+        assert_eq!(
+            check_feasibility(&course([1, 1, 1, 1])),
+            Err(Infeasibility::NotEnoughFuel { needed: 8, available: 4 })
+        );
+        assert_eq!(
+            check_feasibility(&course([0, 5, 5, 5])),
+            Err(Infeasibility::Stranded(Boat::with_fuel(3, 0)))
+        );
+        assert_eq!(cost(&course([3, 3, 1, 1]), &schedule(&course([3, 3, 1, 1])).unwrap()), 45);
+        let (upper, sol) = upper_bound(&course([3, 3, 1, 1])).unwrap();
+        assert_eq!(upper, 45);
+        assert_eq!(sol[1], HarbourMove::Return(Boat::with_fuel(3, 2)));
+        let mixed = Harbour::new_with_fuel(vec![
+            Boat::new(3),
+            Boat::with_fuel(3, 3),
+            Boat::with_fuel(6, 1),
+            Boat::with_fuel(15, 1),
+        ]);
+        let (upper, sol) = upper_bound(&mixed).unwrap();
+        assert_eq!(upper, simple_sol(&[3, 3, 6, 15]).unwrap().0);
+        assert_eq!(sol[1], HarbourMove::Return(Boat::new(3)));
+        let harbour = course([5, 1, 1, 1]);
+        assert_eq!(upper_bound(&harbour), None);
+        let sol = schedule(&harbour).unwrap();
+        assert_eq!(cost(&harbour, &sol), 51);
+        assert_eq!(schedule(&course([2, 2, 2, 2])), Err(Infeasibility::NoSchedule));
     }
-}
\ No newline at end of file
+
+    /// The trips wait for the harbour to open and end before the deadline.
+    #[test]
+    fn test_windows_and_deadline() {
+        let mut harbour = Harbour::new(vec![3, 6, 15, 24]);
+        harbour.windows(vec![(0, 10), (30, 1000)]);
+        let sol = schedule(&harbour).unwrap();
+        let mut state = harbour.clone();
+        for mv in sol.iter() {
+            let start = state.departure(move_time(mv)).unwrap();
+            assert!(start + move_time(mv) <= 10 || start >= 30, "{:?} starts at {}", mv, start);
+            state.modify(mv);
+        }
+        assert_eq!(cost(&harbour, &sol), state.clock);
+        assert_eq!(state.clock, 66);
+        harbour.windows(vec![(0, 20)]);
+        assert_eq!(check_feasibility(&harbour), Err(Infeasibility::NoWindow(Boat::new(24))));
+
+        let mut harbour = Harbour::new(vec![3, 6, 15, 24]);
+        let sol = schedule(harbour.deadline(45)).unwrap();
+        assert_eq!(cost(&harbour, &sol), 45);
+        assert_eq!(schedule(harbour.deadline(44)), Err(Infeasibility::NoSchedule));
+        assert_eq!(
+            schedule(harbour.deadline(38)),
+            Err(Infeasibility::Deadline { lower_bound: 39, deadline: 38 })
+        );
+    }
+}
@@ -1,7 +1,17 @@
+//! The crate for mission2
+//! Contains the harbour problem and its variants so they can be solved with the graphs crate.
+//! The harbour, its boats and the closed form in the harbours.rs file.
+//! The ferry with other capacities and return costs in the ferry.rs file.
+//! The river crossing puzzles in the river.rs file.
+//! The command line interface in the cli.rs file.
+#![deny(missing_docs)]
+extern crate graphs;
+extern crate itertools;
 #[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+extern crate rand;
+extern crate time;
+pub mod harbours;
+pub mod ferry;
+pub mod river;
+pub mod cli;
+mod util;
@@ -1,81 +1,247 @@
-#[deny(missing_docs)]
-/// The crate for mission2
+//! The crate for mission2
+//! Solves the harbour problem and its variants with a star, see *main*.
+#![deny(missing_docs)]
 extern crate rand;
 extern crate graphs;
-extern crate itertools;
+extern crate mission2;
 
 extern crate time;
 use time::PreciseTime;
+use std::env;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process;
+use std::fmt::Debug;
+use std::hash::Hash;
 
 use graphs::graphs::AStar;
 
-mod harbours;
-use harbours::{Harbour, print_sol};
+use mission2::{cli, ferry, harbours, river};
+use cli::{Command, Format, Input, Options};
+use harbours::{Boat, Harbour, HarbourMove, print_sol, simple_sol};
+use ferry::{Ferry, ReturnCost};
 use rand::XorShiftRng;
 use rand::Rng;
 
 
 
-/// This will try to find the best solution for i boats
-/// i ranging from 2 to 50.
-/// Usually it starts getting too long at 13 boats.
+/// Without arguments, runs the examples of the report, see *demo*.
+///
+/// Launching it with `solve <speed>...` or `solve --file <fleet file>` solves the given fleet and
+/// `batch <fleets file> [<output>]` solves one fleet per line of the file and writes a table.
+/// The solver is chosen with `--solver astar|closed-form|bfs` and the output with
+/// `--format text|json`.
 pub fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let options = cli::parse_args(&args).unwrap_or_else(|e| {
+        println!("{}\n{}", e, cli::USAGE);
+        process::exit(2);
+    });
+    match options.command {
+        Command::Demo => demo(),
+        Command::Solve(ref input) => solve(input, &options),
+        Command::Batch(ref path, ref output) => batch(path, output.as_ref(), &options),
+    }
+}
+
+/// Reads the given file.
+/// Exits with an error code if it can not be read.
+fn read_file(path: &str) -> String {
+    let mut text = String::new();
+    if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut text)) {
+        println!("could not read {} : {}", path, e);
+        process::exit(2);
+    }
+    text
+}
+
+/// Solves one fleet and prints the result.
+fn solve(input: &Input, options: &Options) {
+    let ships = match *input {
+        Input::Fleet(ref ships) => ships.clone(),
+        Input::File(ref path) => cli::parse_fleet(&read_file(path)).unwrap_or_else(|e| {
+            println!("could not parse the fleet : {}", e);
+            process::exit(2);
+        }),
+    };
+    let report = cli::solve(&ships, options.solver);
+    match options.format {
+        Format::Text => print!("{}", report.to_text()),
+        Format::Json => println!("{}", report.to_json()),
+    }
+}
+
+/// Solves every fleet of the file and writes the table in the output, or prints it.
+fn batch(path: &str, output: Option<&String>, options: &Options) {
+    let fleets = cli::parse_fleets(&read_file(path)).unwrap_or_else(|e| {
+        println!("could not parse the fleets : {}", e);
+        process::exit(2);
+    });
+    let reports = fleets
+        .iter()
+        .map(|ships| cli::solve(ships, options.solver))
+        .collect::<Vec<cli::Report>>();
+    let table = cli::table(&reports, options.format);
+    match output {
+        Some(output) => {
+            if let Err(e) = File::create(output).and_then(|mut f| f.write_all(table.as_bytes())) {
+                println!("could not write {} : {}", output, e);
+                process::exit(2);
+            }
+            println!("the results of the {} fleets were written to {}", reports.len(), output);
+        }
+        None => print!("{}", table),
+    }
+}
+
+/// This will try to find the best solution for i boats
+/// i ranging from 4 to 40.
+/// Boats of the same speed being interchangeable, 40 boats with 10 speeds stay tractable.
+pub fn demo() {
     // each ship is the time needed in hours to get to the other side.
 
     println!("trying the course example. unit => 1/4h");
     let ships = vec![3, 6, 15, 24];
-    simple_sol(&ships);
-    let start = PreciseTime::now();
-    let sol = AStar::new(Harbour::new(ships.clone())).solve().unwrap_or_else(||panic!("fail"));
-    let end = PreciseTime::now();
-    let diff = start.to(end).num_milliseconds();
-    println!(" found a solution in : {} ms", diff);
+    let (time, _) = simple_sol(&ships).unwrap();
+    println!("the linearithmic solution takes : {}", time);
+    let sol = gap_report(&Harbour::new(ships.clone()));
     println!("the solution found is : \n\n");
-    print_sol(sol);
+    print_sol(&Harbour::new(ships.clone()), sol);
     println!("\n\n");
 
 
     let mut my_rand = XorShiftRng::new_unseeded();
-    for i in 4..15 {
+    for i in (4..15).chain([20, 30, 40].iter().cloned()) {
         println!("trying to find a solution for {} ships", i);
-        let  ships = my_rand.gen_iter().take(i).map(|i : i32 | (i.abs() % 10 +1) as i32 ).collect::<Vec<i32>>();
+        let  ships = my_rand.gen_iter().take(i).map(|i : i32 | i.abs() % 10 + 1).collect::<Vec<i32>>();
         println!("the ships are : {:?}", ships);
-        let start = PreciseTime::now();
-        let sol = AStar::new(Harbour::new(ships.clone())).solve();
-        let end = PreciseTime::now();
-        let diff = start.to(end).num_milliseconds();
-        println!(" found a solution in : {} ms", diff);
+        println!("the linearithmic solution takes : {}", simple_sol(&ships).unwrap().0);
+        let sol = gap_report(&Harbour::new(ships.clone()));
         println!("the solution found is : \n\n");
-        print_sol(sol.unwrap());
+        print_sol(&Harbour::new(ships.clone()), sol);
         println!("\n\n")
     }
-}
 
+    println!("trying the course example where the boat of size 3 can only make 3 trips.");
+    let harbour = Harbour::new_with_fuel(vec![
+        Boat::with_fuel(3, 3),
+        Boat::new(6),
+        Boat::new(15),
+        Boat::new(24),
+    ]);
+    solve_constrained(&harbour);
+
+    println!("trying the course example where the harbour closes between 10 and 30.");
+    let mut harbour = Harbour::new(vec![3, 6, 15, 24]);
+    harbour.windows(vec![(0, 10), (30, 1000)]);
+    solve_constrained(&harbour);
+
+    println!("trying the course example with a deadline at 44.");
+    let mut harbour = Harbour::new(vec![3, 6, 15, 24]);
+    harbour.deadline(44);
+    solve_constrained(&harbour);
+
+    println!("trying the course example where every boat can only make 2 trips.");
+    let harbour = Harbour::new_with_fuel(vec![3, 6, 15, 24].into_iter().map(|s| Boat::with_fuel(s, 2)).collect());
+    solve_constrained(&harbour);
+
+    println!("trying the classic river crossing puzzles.");
+    solve_river("the wolf, the goat and the cabbage", &river::wolf_goat_cabbage());
+    solve_river("3 missionaries and 3 cannibals", &river::missionaries(3, 2));
+    solve_river("3 jealous couples", &river::jealous_husbands(3, 2));
+    solve_river("the course example as a river crossing", &river::harbour(vec![3, 6, 15, 24]));
+
+    println!("trying the course example with three boats crossing together.");
+    let mut ferry = Ferry::new(vec![3, 6, 15, 24]);
+    ferry.capacity(3);
+    solve_ferry(&ferry);
 
-/// This is the solution in linearithmic time explained at the end of the report.
-pub fn simple_sol(ships: &Vec<i32>) -> Vec<(i32, i32)>{
-    let mut sol = vec![];
-    let mut s_ships = ships.clone();
-    s_ships.sort();
-    sol.push((s_ships[0], s_ships[1]));
-    //println!("move {} and {} to end and bring {} back.", s_ships[0], s_ships[1], s_ships[0]);
-    while !s_ships.is_empty() {
-        if s_ships.len() > 2 {
-            sol.push((s_ships[0], s_ships[2]));
-            //println!("move {} and {} to end", s_ships[0], s_ships[2]);
-            break;
+    println!("trying the course example where only the two slowest boats can come back.");
+    let mut ferry = Ferry::new(vec![3, 6, 15, 24]);
+    ferry.returners(vec![15, 24]);
+    solve_ferry(&ferry);
+
+    println!("trying 6 boats, 3 crossing together and every return taking 5.");
+    let mut ferry = Ferry::new(vec![2, 3, 7, 11, 13, 19]);
+    ferry.capacity(3).return_cost(ReturnCost::Fixed(5));
+    solve_ferry(&ferry);
+}
+
+/// Schedules the crossing under the constraints of the harbour and prints it, or prints why it
+/// is impossible.
+fn solve_constrained(harbour: &Harbour) {
+    let start = PreciseTime::now();
+    let sol = harbours::schedule(harbour);
+    let end = PreciseTime::now();
+    println!(" searched for : {} ms", start.to(end).num_milliseconds());
+    match sol {
+        Ok(sol) => {
+            println!("the solution found takes : {}", harbours::cost(harbour, &sol));
+            println!("the solution found is : \n\n");
+            print_sol(harbour, sol);
         }
-        let bigger = s_ships.pop().unwrap();
-        if s_ships[1]*2-s_ships[1] < *s_ships.last().unwrap(){
-            sol.push((*s_ships.last().unwrap(), bigger));
-            sol.push((s_ships[0], s_ships[1]));
-            //println!("move {} and {} to end and bring {} back.", s_ships.last().unwrap(), bigger, s_ships[1]);
-            //println!("move {} and {} to end and bring {} back.", s_ships[0], s_ships[1], s_ships[0]);
-            s_ships.pop();
-        } else {
-            sol.push((s_ships[0], bigger));
-            //println!("move {} and {} to end and bring {} back.", s_ships[0], bigger, s_ships[0]);
+        Err(e) => println!("there is no solution : {}", e),
+    }
+    println!("\n\n");
+}
+
+/// Solves a river crossing puzzle and prints the solution.
+fn solve_river<I: Ord + Hash + Clone + Debug>(name: &str, puzzle: &river::River<I>) {
+    println!("solving {}", name);
+    match AStar::new(puzzle.clone()).solve() {
+        Some(sol) => {
+            println!("the solution found takes {} trips : \n", sol.len());
+            river::print_sol(puzzle, sol);
         }
+        None => println!("there is no solution"),
     }
+    println!("\n\n");
+}
+
+/// Prints the bounds of the instance and the gap between them, then solves it with the a star
+/// pruned by the upper bound and prints how far the optimal time is from each bound.
+fn gap_report(harbour: &Harbour) -> Vec<HarbourMove> {
+    let lower = harbours::lower_bound(harbour);
+    let (upper, _) = harbours::upper_bound(harbour).expect("the fleet has no constraints");
+    println!(
+        "lower bound : {}, upper bound : {}, gap : {:.1}%",
+        lower,
+        upper,
+        100.0 * (upper - lower) as f64 / upper.max(1) as f64
+    );
+    let start = PreciseTime::now();
+    let mut astar = AStar::new(harbour.clone());
+    let sol = astar.upper_bound(upper).solve().unwrap();
+    let end = PreciseTime::now();
+    println!(
+        " found a solution in : {} ms, {} states expanded, {} pruned by the upper bound",
+        start.to(end).num_milliseconds(),
+        astar.expansions(),
+        astar.prunings()
+    );
+    let time = harbours::cost(harbour, &sol);
+    println!(
+        "the solution found takes : {} (lower bound + {}, upper bound - {})",
+        time,
+        time - lower,
+        upper - time
+    );
     sol
-}
\ No newline at end of file
+}
+
+/// Solves an instance of the generalized problem and prints the solution.
+pub fn solve_ferry(ferry: &Ferry) {
+    let start = PreciseTime::now();
+    let sol = AStar::new(ferry.clone()).solve();
+    let end = PreciseTime::now();
+    let diff = start.to(end).num_milliseconds();
+    println!(" found a solution in : {} ms", diff);
+    match sol {
+        Some(sol) => {
+            println!("the solution found takes : {}\n\n", ferry::cost(ferry, &sol));
+            ferry::print_sol(sol);
+        }
+        None => println!("there is no solution."),
+    }
+    println!("\n\n");
+}
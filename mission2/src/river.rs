@@ -0,0 +1,284 @@
+//! A family of river crossing puzzles : items cross a river in a boat of limited capacity,
+//! the banks and the boat must stay safe.
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+use graphs::graphs::StateNode;
+use itertools::Itertools;
+use util::remove_at;
+
+#[derive(Clone, Debug)]
+/// A state of a river crossing puzzle.
+/// Every item starts on the left bank and the boat must bring them all to the right bank.
+/// The banks are kept sorted so that the same items on each side give the same state.
+pub struct River<I: Ord + Hash + Clone + Debug> {
+    left: Vec<I>,
+    right: Vec<I>,
+    /// True if the boat is on the left bank.
+    boat_left: bool,
+    /// The maximum number of items in the boat.
+    capacity: usize,
+    /// Returns true if the item can row, every trip needs someone to row.
+    rower: fn(&I) -> bool,
+    /// Returns true if the given items can be left together, on a bank or in the boat.
+    safe: fn(&[I]) -> bool,
+    /// Returns the duration of a trip with the given items in the boat.
+    trip_cost: fn(&[I]) -> i32,
+}
+
+/// The rules are the same for every state of a puzzle, so only the banks and the boat are
+/// compared.
+impl<I: Ord + Hash + Clone + Debug> PartialEq for River<I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.boat_left == other.boat_left && self.left == other.left && self.right == other.right
+    }
+}
+
+impl<I: Ord + Hash + Clone + Debug> Eq for River<I> {}
+
+impl<I: Ord + Hash + Clone + Debug> Hash for River<I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.left.hash(state);
+        self.right.hash(state);
+        self.boat_left.hash(state);
+    }
+}
+
+/// Any item can row.
+fn anyone<I>(_item: &I) -> bool {
+    true
+}
+
+/// Every trip takes one unit of time.
+fn one_unit<I>(_items: &[I]) -> i32 {
+    1
+}
+
+impl<I: Ord + Hash + Clone + Debug> River<I> {
+    /// Creates a puzzle where the items start on the left bank.
+    /// By default anyone can row, every group is safe and every trip takes one unit of time.
+    pub fn new(mut items: Vec<I>, capacity: usize) -> Self {
+        assert!(capacity > 0, "the boat must carry someone to row");
+        items.sort();
+        River {
+            left: items,
+            right: vec![],
+            boat_left: true,
+            capacity,
+            rower: anyone,
+            safe: |_| true,
+            trip_cost: one_unit,
+        }
+    }
+    /// Only allows the items satisfying the predicate to row.
+    pub fn rower(&mut self, rower: fn(&I) -> bool) -> &mut Self {
+        self.rower = rower;
+        self
+    }
+    /// Only allows the groups satisfying the predicate, on the banks and in the boat.
+    pub fn safe(&mut self, safe: fn(&[I]) -> bool) -> &mut Self {
+        self.safe = safe;
+        self
+    }
+    /// Sets the duration of a trip from the items in the boat.
+    pub fn trip_cost(&mut self, trip_cost: fn(&[I]) -> i32) -> &mut Self {
+        self.trip_cost = trip_cost;
+        self
+    }
+    /// Returns the items on the bank of the boat and on the other one.
+    fn banks(&self) -> (&Vec<I>, &Vec<I>) {
+        match self.boat_left {
+            true => (&self.left, &self.right),
+            false => (&self.right, &self.left),
+        }
+    }
+}
+
+impl<I: Ord + Hash + Clone + Debug> StateNode<Vec<I>> for River<I> {
+    /// Every group of at most *capacity* items on the bank of the boat can cross if one of them
+    /// rows and if the group, the bank it leaves and the bank it reaches are safe.
+    fn moves(&self) -> Vec<Vec<I>> {
+        let (from, to) = self.banks();
+        let mut groups = (1..self.capacity.min(from.len()) + 1)
+            .flat_map(|k| from.iter().cloned().combinations(k).collect::<Vec<Vec<I>>>())
+            .collect::<Vec<Vec<I>>>();
+        groups.sort();
+        groups.dedup();
+        groups
+            .into_iter()
+            .filter(|group| group.iter().any(|i| (self.rower)(i)) && (self.safe)(group))
+            .filter(|group| {
+                let mut left_behind = from.clone();
+                for item in group {
+                    remove_at(&mut left_behind, item);
+                }
+                let mut reached = to.clone();
+                reached.extend(group.iter().cloned());
+                reached.sort();
+                (self.safe)(&left_behind) && (self.safe)(&reached)
+            })
+            .collect()
+    }
+    /// Brings the group to the other bank with the boat.
+    fn modify(&mut self, mv: &Vec<I>) {
+        {
+            let (from, to) = match self.boat_left {
+                true => (&mut self.left, &mut self.right),
+                false => (&mut self.right, &mut self.left),
+            };
+            for item in mv {
+                remove_at(from, item);
+                to.push(item.clone());
+            }
+            to.sort();
+        }
+        self.boat_left = !self.boat_left;
+    }
+    /// If the left bank is empty, every item crossed.
+    fn end(&self) -> bool {
+        self.left.is_empty()
+    }
+    /// The trip takes the time given by the puzzle.
+    fn cost_to(&self, _target: &Self, mv: &Vec<I>) -> i32 {
+        (self.trip_cost)(mv)
+    }
+    /// Nothing is known about the costs, so the a star behaves as a dijkstra.
+    fn dist_from_end(&self) -> i32 {
+        0
+    }
+}
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// The items of the wolf, goat and cabbage puzzle.
+pub enum Farm {
+    /// The only one able to row.
+    Farmer,
+    /// Eats the goat if the farmer is not there.
+    Wolf,
+    /// Eats the cabbage if the farmer is not there.
+    Goat,
+    /// Waits to be eaten.
+    Cabbage,
+}
+
+/// The farmer brings a wolf, a goat and a cabbage across the river, with room for one of them.
+pub fn wolf_goat_cabbage() -> River<Farm> {
+    let mut river = River::new(vec![Farm::Farmer, Farm::Wolf, Farm::Goat, Farm::Cabbage], 2);
+    river.rower(|i| *i == Farm::Farmer).safe(|bank| {
+        bank.contains(&Farm::Farmer) ||
+            !(bank.contains(&Farm::Goat) &&
+                  (bank.contains(&Farm::Wolf) || bank.contains(&Farm::Cabbage)))
+    });
+    river
+}
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// The items of the missionaries and cannibals puzzle.
+pub enum Person {
+    /// Must never be outnumbered by the cannibals.
+    Missionary,
+    /// Eats the missionaries if they outnumber them.
+    Cannibal,
+}
+
+/// The given number of missionaries and cannibals cross the river, the missionaries must never
+/// be outnumbered by the cannibals.
+pub fn missionaries(nb: usize, capacity: usize) -> River<Person> {
+    let mut people = vec![Person::Missionary; nb];
+    people.extend(vec![Person::Cannibal; nb]);
+    let mut river = River::new(people, capacity);
+    river.safe(|group| {
+        let m = group.iter().filter(|p| **p == Person::Missionary).count();
+        m == 0 || m >= group.len() - m
+    });
+    river
+}
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// The items of the jealous husbands puzzle, the number identifies the couple.
+pub enum Spouse {
+    /// The husband of the couple.
+    Husband(usize),
+    /// The wife of the couple.
+    Wife(usize),
+}
+
+/// The given number of couples cross the river, no wife may be with another man unless her
+/// husband is present.
+pub fn jealous_husbands(nb_couples: usize, capacity: usize) -> River<Spouse> {
+    let people = (0..nb_couples)
+        .flat_map(|c| vec![Spouse::Husband(c), Spouse::Wife(c)])
+        .collect();
+    let mut river = River::new(people, capacity);
+    river.safe(|group| {
+        let has_men = group.iter().any(|s| match *s {
+            Spouse::Husband(_) => true,
+            Spouse::Wife(_) => false,
+        });
+        group.iter().all(|s| match *s {
+            Spouse::Wife(c) => !has_men || group.contains(&Spouse::Husband(c)),
+            Spouse::Husband(_) => true,
+        })
+    });
+    river
+}
+
+/// The harbour problem as a river crossing : every boat can row, two boats cross together at
+/// the speed of the slowest one.
+pub fn harbour(ships: Vec<i32>) -> River<i32> {
+    let mut river = River::new(ships, 2);
+    river.trip_cost(|group| *group.iter().max().unwrap());
+    river
+}
+
+/// Pretty Prints the solution played from the given state.
+pub fn print_sol<I: Ord + Hash + Clone + Debug>(start: &River<I>, solution: Vec<Vec<I>>) {
+    let mut state = start.clone();
+    for mv in solution {
+        let side = match state.boat_left {
+            true => "right",
+            false => "left",
+        };
+        println!("brings {:?} to the {} bank", mv, side);
+        state.modify(&mv);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use graphs::graphs::{dfs, AStar};
+
+    /// Returns the number of trips of the shortest solution, also checking that a dfs finds one.
+    fn nb_trips<I: Ord + Hash + Clone + Debug>(river: River<I>) -> Option<usize> {
+        assert_eq!(dfs(river.clone()).is_some(), AStar::new(river.clone()).solve().is_some());
+        AStar::new(river).solve().map(|sol| sol.len())
+    }
+
+    #[test]
+    fn test_classic_puzzles() {
+        assert_eq!(nb_trips(wolf_goat_cabbage()), Some(7));
+        assert_eq!(nb_trips(missionaries(3, 2)), Some(11));
+        assert_eq!(nb_trips(missionaries(4, 2)), None);
+        assert_eq!(nb_trips(missionaries(4, 3)), Some(9));
+        assert_eq!(nb_trips(jealous_husbands(3, 2)), Some(11));
+        assert_eq!(nb_trips(jealous_husbands(4, 2)), None);
+    }
+
+    /// The river crossing finds the same time as the harbour model.
+    #[test]
+    fn test_harbour_instance() {
+        for ships in [vec![3, 6, 15, 24], vec![1, 2, 5, 10], vec![2, 2, 3, 7, 9, 9]].iter() {
+            let river = harbour(ships.clone());
+            let sol = AStar::new(river.clone()).solve().unwrap();
+            let mut state = river.clone();
+            let mut time = 0;
+            for mv in sol.iter() {
+                time += (river.trip_cost)(mv);
+                state.modify(mv);
+            }
+            assert!(state.end());
+            assert_eq!(time, ::harbours::simple_sol(ships).unwrap().0, "for {:?}", ships);
+        }
+    }
+}
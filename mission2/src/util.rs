@@ -0,0 +1,8 @@
+//! Small helpers shared by the models of the crate.
+use std::fmt::Debug;
+
+/// Removes an element from a vector.
+pub fn remove_at<U: Eq + Debug>(vec: &mut Vec<U>, elem: &U) {
+    let to_rem = vec.iter().position(|s| s == elem).unwrap();
+    vec.remove(to_rem);
+}
@@ -1,16 +1,62 @@
 /// Module for the basic AI behaviours.
 /// This contains the trait for ais and anarena to test them.
 /// To create an ai just implements the *AI* trait and launch it in the arena.
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use game::Game;
-use unit::Action;
+use unit::{Action, Turn};
+use rules::{Rules, StallRule};
 use rand::{XorShiftRng};
 
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// How much an ai may search for each of its moves.
+/// Giving the same budget to two ais makes their fight fair, whatever their depth or iterations.
+pub enum Budget {
+    /// The ai searches as it was created : to its depth or for its iterations.
+    Default,
+    /// The ai plays within the given time.
+    Time(Duration),
+    /// The ai evaluates at most the given number of states of the game.
+    Nodes(usize),
+    /// The ai makes at most the given number of iterations of its search.
+    Iterations(usize),
+}
+
+impl Budget {
+    /// Returns the time before wich a move started now must be played, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        match *self {
+            Budget::Time(time) => Some(Instant::now() + time),
+            _ => None,
+        }
+    }
+}
+
 /// The AI trait.
-/// An AI only have to return an action given a game and a player.
+/// An AI only have to return an action given a game and a player, within its budget.
+/// When several units act in a turn, the AI chooses their actions one after the other, wich
+/// avoids searching all their combinations at once.
 pub trait AI {
     /// Returns an action, given a player and the state of the game.
     fn play(&mut self, game: &Game, player: usize) -> Action;
+    /// Sets the budget of the searches of the next moves.
+    /// The ais wich do not search, like the random ai, play within any budget and ignore it.
+    fn budget(&mut self, _budget: Budget) {}
+    /// Returns the actions of the whole turn of the player, chosen one after the other with
+    /// *play*.
+    fn play_turn(&mut self, game: &Game, player: usize) -> Turn {
+        let mut game = game.clone();
+        let mut turn = Turn::default();
+        loop {
+            let action = self.play(&game, player);
+            game.apply_move(&action);
+            turn.actions.push(action);
+            if game.next_player(player) != player {
+                return turn;
+            }
+        }
+    }
 }
 
 /// The arena struct, used to test ais.
@@ -33,6 +79,10 @@ pub struct Arena {
     progression_step: usize,
     /// The number of units that we add/remove every progression step.
     progression_units: usize,
+    /// The rules of the games.
+    rules: Rc<Rules>,
+    /// The budget of both ais for each move.
+    budget: Budget,
 }
 
 impl Arena {
@@ -46,8 +96,21 @@ impl Arena {
             max_turns: max_turns,
             progression_step: 1,
             progression_units: 0,
+            rules: Rc::new(Rules::default()),
+            budget: Budget::Default,
         }
     }
+    /// Sets the budget of both ais for each move, the ais searching as they were created by
+    /// default.
+    pub fn budget(&mut self, budget: Budget) -> &mut Self {
+        self.budget = budget;
+        self
+    }
+    /// Sets the rules of the games played in the arena.
+    pub fn rules(&mut self, rules: Rules) -> &mut Self {
+        self.rules = Rc::new(rules);
+        self
+    }
     /// Sets the progression of the unbalance between the players.
     /// every *step* games the difference in units decrease by *units*
     pub fn progression(&mut self, step: usize, units: usize) -> &mut Self {
@@ -70,10 +133,11 @@ impl Arena {
         let mut wons = [0, 0];
         let mut advantages = [0, 0];
         for i in 1..self.game_number +1 {
-            let mut game = Game::new_random(
+            let mut game = Game::new_random_with_rules(
                 self.game_size,
                 self.game_size,
                 self.game_units + advantages[1],
+                self.rules.clone(),
                 &mut my_rand,
             );
             game.remove_unit_for_player(0, advantages[0]);
@@ -99,6 +163,8 @@ impl Arena {
     /// returns a tuple of the wins of the first and second ai
     pub fn rounds(&self, game: &Game, ai: &mut AI, ai2: &mut AI) -> (usize, usize) {
         let mut wons = [0, 0];
+        ai.budget(self.budget);
+        ai2.budget(self.budget);
         let res = Self::resolve(&mut game.clone(), &mut [ai, ai2], self.max_turns);
         wons[res] += 1;
         if self.verbose {
@@ -114,34 +180,23 @@ impl Arena {
 
     /// Given a game's starting state, and two ais in an array, make them play and returns the id of
     /// the winner.
-    /// The ai with index 0 in the array will start first.
-    ///
-    /// If no unit dies for *nb_rounds* turns then
+    /// The ai with index 0 in the array plays the player 0, the current player of the game
+    /// starting.
     ///
-    /// returns the id of the player with the most unit still alive
-    /// If the units are the same returns the id of the player who started playing.
+    /// The game ends as told by *Game::outcome*. If the rules of the game have no stall rule, the
+    /// player with the most unit still alive wins when no unit died for *nb_rounds* rounds.
+    /// A draw is won by the player 0.
     pub fn resolve(game: &mut Game, ais: &mut [&mut AI], nb_rounds: usize) -> usize {
-        let mut rounds_since_last_death = 0;
-        let mut alive_units = game.player(0).len() + game.player(1).len();
-        'a: loop {
-            for i in 0..2 {
-                if game.player(i).len() > 0 && game.moves(i).len() > 0 {
-                    let mv = &ais[i].play(&game, i);
-                    game.apply_move(mv);
-                } else {
-                    return 1 - i;
-                }
-            }
-            let new_unit_count = game.player(0).len() + game.player(1).len();
-            if new_unit_count != alive_units {
-                rounds_since_last_death += 0;
-                alive_units = new_unit_count;
-            }
-            rounds_since_last_death += 1;
-            if rounds_since_last_death > nb_rounds {
-                break;
+        if game.rules().stall == StallRule::Never {
+            game.set_stall(StallRule::MostUnits(2 * nb_rounds));
+        }
+        loop {
+            if let Some(outcome) = game.outcome() {
+                return outcome.winner().unwrap_or(0);
             }
+            let player = game.current_player();
+            let turn = ais[player].play_turn(game, player);
+            game.apply_turn(&turn);
         }
-        (game.player(1).len() > game.player(0).len()) as usize
     }
 }
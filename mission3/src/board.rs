@@ -0,0 +1,47 @@
+//! The occupancy grid of a game : for every cell, the unit standing on it if any.
+//! It gives the unit at a position without looking at every unit.
+use unit::{Position, Unit};
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+/// The occupants of the cells of a grid.
+pub struct Board {
+    /// The width of the grid.
+    width: usize,
+    /// The height of the grid.
+    height: usize,
+    /// The team and the index in its team of the unit on each cell, column after column.
+    cells: Vec<Option<(usize, usize)>>,
+}
+
+impl Board {
+    /// Creates the board of the given units, indexed by team.
+    pub fn new(width: usize, height: usize, players: &[Vec<Unit>]) -> Self {
+        let mut board = Board {
+            width,
+            height,
+            cells: vec![None; width * height],
+        };
+        for (team, units) in players.iter().enumerate() {
+            for (index, unit) in units.iter().enumerate() {
+                board.set(unit.pos, Some((team, index)));
+            }
+        }
+        board
+    }
+    /// Returns the team and the index in its team of the unit at the given position.
+    /// Outside of the grid, there is nobody.
+    pub fn get(&self, pos: Position) -> Option<(usize, usize)> {
+        match pos.x < self.width && pos.y < self.height {
+            true => self.cells[pos.x * self.height + pos.y],
+            false => None,
+        }
+    }
+    /// Sets the occupant of the given position, wich must be in the grid.
+    pub fn set(&mut self, pos: Position, occupant: Option<(usize, usize)>) {
+        self.cells[pos.x * self.height + pos.y] = occupant;
+    }
+    /// Returns true if a unit stands at the given position.
+    pub fn occupied(&self, pos: Position) -> bool {
+        self.get(pos).is_some()
+    }
+}
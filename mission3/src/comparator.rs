@@ -39,6 +39,10 @@ pub fn get_all_evals() -> Vec<(Box<(Evaluator)>, String)> {
             unit_eval_double(),
             "double unit counting with coef".to_string()
         ),
+        (
+            hit_points_eval_double(),
+            "double hit points counting with coef".to_string()
+        ),
         (all_combined(), "all combined".to_string()),
     ]
 }
@@ -85,6 +89,18 @@ pub fn unit_eval_double() -> Box<Evaluator> {
         .use_ennemy(Box::new(AliveUnitsEvaluator::new(1, 1, 1)));
     Box::new(unit_eval_double)
 }
+/// Counts our remaining hit points minus the ennemy's, counting tower as 5 and gunner as 3.
+pub fn hit_points_eval_double() -> Box<Evaluator> {
+    let mut ally = AliveUnitsEvaluator::new(5, 1, 3);
+    ally.count_hit_points();
+    let mut ennemy = AliveUnitsEvaluator::new(5, 1, 3);
+    ennemy.count_hit_points();
+    let mut hit_points_eval_double = CombinedEvaluator::new();
+    hit_points_eval_double
+        .use_ally(Box::new(ally))
+        .use_ennemy(Box::new(ennemy));
+    Box::new(hit_points_eval_double)
+}
 /// Counts how many of our units are still alive.
 pub fn all_combined() -> Box<Evaluator> {
     let mut unit_eval_double = CombinedEvaluator::new();
@@ -10,34 +10,48 @@ pub trait Evaluator {
 
 /// The evaluator counting units alive in my team.
 pub struct AliveUnitsEvaluator {
-    /// The value of having/killing a tower.
-    tower: usize,
-    /// The value of having/killing an infantry.
-    infantry: usize,
-    /// The value of a gunner.
-    gunner: usize,
+    /// The value of having/killing an unit of each type, indexed by *UnitType*.
+    /// The types without a value are worth 1.
+    values: Vec<usize>,
+    /// If true, every remaining hit point of an unit is worth its value.
+    hit_points: bool,
 }
 impl AliveUnitsEvaluator {
-    /// Creates a new alive units evaluator.
+    /// Creates a new alive units evaluator for the classic rules.
     /// Takes the estimated values of the different units.
     pub fn new(tower: usize, infantry: usize, gunner: usize) -> Self {
+        let mut values = vec![0; 3];
+        values[UnitType::MOBILE_TOWER.0] = tower;
+        values[UnitType::INFANTRYMAN.0] = infantry;
+        values[UnitType::GUNNER.0] = gunner;
+        AliveUnitsEvaluator::with_values(values)
+    }
+    /// Creates a new alive units evaluator with the values of each type of the rules.
+    pub fn with_values(values: Vec<usize>) -> Self {
         AliveUnitsEvaluator {
-            tower: tower,
-            infantry: infantry,
-            gunner: gunner,
+            values,
+            hit_points: false,
         }
     }
+    /// Scores the remaining hit points of the units instead of counting them, so that wounding an
+    /// unit is better than nothing.
+    pub fn count_hit_points(&mut self) -> &mut Self {
+        self.hit_points = true;
+        self
+    }
 }
 impl Evaluator for AliveUnitsEvaluator {
-    /// Counts the summed value of all units.
+    /// Counts the summed value of all units, or of all their hit points.
     fn eval(&self, game: &Game, player: usize) -> i32 {
         game.player(player)
             .iter()
-            .map(|unit| match unit.unit_type {
-                UnitType::Infantryman => self.infantry,
-                UnitType::MobileTower => self.tower,
-                UnitType::Gunner => self.gunner,
-            } as i32)
+            .map(|unit| {
+                let value = *self.values.get(unit.unit_type.0).unwrap_or(&1) as i32;
+                match self.hit_points {
+                    true => value * unit.hp as i32,
+                    false => value,
+                }
+            })
             .sum::<i32>()
     }
 }
@@ -53,15 +67,7 @@ impl InfluenceEvaluator {
 impl Evaluator for InfluenceEvaluator {
     /// Calculate the number of cells in wich our team can shoot.
     fn eval(&self, game: &Game, player: usize) -> i32 {
-        let mut inf = 0;
-        let mut targets = vec![];
-        for action in game.moves(player) {
-            if !targets.contains(&action.shoot) {
-                inf += 1;
-                targets.push(action.shoot.clone());
-            }
-        }
-        inf
+        game.reach(player).len() as i32
     }
 }
 
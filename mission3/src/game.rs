@@ -1,6 +1,13 @@
 /// The game actualy.
+/// The game keeps the occupants of every cell and a Zobrist hash of its state up to date after
+/// every move, and the moves can be undone, so that the ais search without copying the game.
 use std::fmt;
-use unit::{Unit, Action, Position};
+use std::rc::Rc;
+use unit::{Unit, UnitType, Action, Position, Turn};
+use std::cmp::Ordering;
+use rules::{Rules, StallRule, TurnMode};
+use terrain::{Map, MapError, Terrain};
+use board::Board;
 use rand::{XorShiftRng};
 
 
@@ -8,32 +15,241 @@ use rand::{XorShiftRng};
 /// The game's struct.
 /// Contains a state of the game.
 pub struct Game {
-    /// The width of the game's grid.
-    width: usize,
-    /// The height of the game's grid.
-    height: usize,
+    /// The terrain of the game's grid, shared by all the states of a game.
+    map: Rc<Map>,
     /// A two dimensional vector containing the list of units for each players.
     players: Vec<Vec<Unit>>,
+    /// The rules of the game, shared by all the states of a game.
+    rules: Rc<Rules>,
+    /// The player whose turn is in progress, none between two turns.
+    acting: Option<usize>,
+    /// The action points spent by the acting player during its turn.
+    spent: u32,
+    /// The player whose turn it is, or will be once the turn in progress starts.
+    current: usize,
+    /// The number of turns played.
+    turn: usize,
+    /// The number of turns ended since the last death, the turn of the death included.
+    stalled: usize,
+    /// The occupants of the cells of the grid.
+    board: Board,
+    /// The Zobrist hash of the state : the xor of the keys of the units and of the turn.
+    hash: u64,
 }
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// Why a game ended.
+pub enum Reason {
+    /// The loser has no unit left.
+    Eliminated,
+    /// None of the units of the loser can act at the begining of its turn.
+    Stuck,
+    /// Nobody died for the turns of the stall rule, see *StallRule*.
+    Stalled,
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// How a game ended.
+pub enum Outcome {
+    /// The given player won.
+    Win(usize, Reason),
+    /// Nobody won.
+    Draw(Reason),
+}
+
+impl Outcome {
+    /// Returns the winner, if any.
+    pub fn winner(&self) -> Option<usize> {
+        match *self {
+            Outcome::Win(player, _) => Some(player),
+            Outcome::Draw(_) => None,
+        }
+    }
+}
+
+/// A change made to the units by a move, to be reverted by *Game::undo_move*.
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Change {
+    /// The unit at the given team and index was this one before the move.
+    Updated(usize, usize, Unit),
+    /// This unit was removed from the given team and index.
+    Removed(usize, usize, Unit),
+}
+
+/// What is needed to undo a move, returned by *Game::apply_move*.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Undo {
+    /// The hash before the move.
+    hash: u64,
+    /// The acting player before the move.
+    acting: Option<usize>,
+    /// The action points spent before the move.
+    spent: u32,
+    /// The current player, the turns played and the turns without death before the move.
+    turns: (usize, usize, usize),
+    /// The changes made to the units, in the order they were made.
+    changes: Vec<Change>,
+}
+
+/// Mixes the bits of a number, giving the pseudo random keys of the Zobrist hash (splitmix64).
+fn mix(key: u64) -> u64 {
+    let mut z = key.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 impl Game {
     /// Creates a new game with the desired dimension and the given starting units.
+    /// The game uses the classic rules.
     pub fn new(width: usize, height: usize, players: Vec<Vec<Unit>>) -> Self {
-        Game {
-            height: height,
-            width: width,
-            players: players,
+        Game::new_with_rules(width, height, players, Rc::new(Rules::default()))
+    }
+    /// Creates a new game with the desired dimension, the given starting units and rules.
+    /// The grid has plain ground everywhere.
+    pub fn new_with_rules(
+        width: usize,
+        height: usize,
+        players: Vec<Vec<Unit>>,
+        rules: Rc<Rules>,
+    ) -> Self {
+        Game::new_with_map(Map::new(width, height), players, rules)
+    }
+    /// Creates a new game on the given map with the given starting units and rules.
+    /// The units start with the hit points of their type.
+    pub fn new_with_map(map: Map, mut players: Vec<Vec<Unit>>, rules: Rc<Rules>) -> Self {
+        for unit in players.iter_mut().flat_map(|p| p.iter_mut()) {
+            unit.hp = rules.unit(&unit.unit_type).hit_points;
+        }
+        let board = Board::new(map.width(), map.height(), &players);
+        let mut game = Game {
+            map: Rc::new(map),
+            players,
+            rules,
+            acting: None,
+            spent: 0,
+            current: 0,
+            turn: 0,
+            stalled: 0,
+            board,
+            hash: 0,
+        };
+        game.rebuild();
+        game
+    }
+    /// Computes the board and the hash from scratch, after the units were changed directly.
+    fn rebuild(&mut self) {
+        self.board = Board::new(self.width(), self.height(), &self.players);
+        self.hash = self.players
+            .iter()
+            .flat_map(|p| p.iter())
+            .fold(self.turn_key(), |hash, unit| hash ^ self.unit_key(unit));
+    }
+    /// Returns the Zobrist hash of the game's state : two games with the same units and turn in
+    /// progress on the same grid, and with a stall rule the same turns since the last death, have
+    /// the same hash.
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+    /// Returns the key of the unit in the hash, depending on its cell, type, hit points, team,
+    /// whether it acted and what the shots from afar added in cover.
+    fn unit_key(&self, unit: &Unit) -> u64 {
+        let cell = (unit.pos.x * self.height() + unit.pos.y) as u64;
+        let key = mix(
+            cell | (unit.unit_type.0 as u64) << 20 | (u64::from(unit.hp)) << 36 |
+                (unit.team as u64) << 52 | (unit.acted as u64) << 60,
+        );
+        mix(key ^ u64::from(unit.grazed))
+    }
+    /// Returns the key of the turn in the hash, depending on the current player, whether its
+    /// turn started and the action points it spent.
+    /// With a stall rule, it also depends on the turns since the last death, the outcome of the
+    /// game depending on them.
+    fn turn_key(&self) -> u64 {
+        let started = self.acting.is_some() as u64;
+        let key = mix(1 << 63 | started << 62 | (self.current as u64) << 32 | u64::from(self.spent));
+        match self.rules.stall {
+            StallRule::Never => key,
+            _ => mix(key ^ self.stalled as u64),
+        }
+    }
+    /// Sets the turns since the last death, keeping the hash up to date.
+    fn set_stalled(&mut self, stalled: usize) {
+        self.hash ^= self.turn_key();
+        self.stalled = stalled;
+        self.hash ^= self.turn_key();
+    }
+    /// Sets the stall rule of the game, replacing the one of its rules.
+    pub fn set_stall(&mut self, stall: StallRule) -> &mut Self {
+        Rc::make_mut(&mut self.rules).stall = stall;
+        self.rebuild();
+        self
+    }
+    /// Returns the player whose turn it is.
+    pub fn current_player(&self) -> usize {
+        self.current
+    }
+    /// Returns the number of turns played, the turns of both players being counted.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+    /// Returns how the game ended, or None while it goes on.
+    ///
+    /// A player loses when it has no unit left or when none of its units can act at its turn.
+    /// When nobody died for long enough, the stall rule of the rules decides the outcome.
+    pub fn outcome(&self) -> Option<Outcome> {
+        for player in 0..self.players.len() {
+            if self.players[player].is_empty() {
+                return Some(Outcome::Win(1 - player, Reason::Eliminated));
+            }
+        }
+        if self.moves(self.current).is_empty() {
+            return Some(Outcome::Win(1 - self.current, Reason::Stuck));
+        }
+        match self.rules.stall {
+            StallRule::Draw(turns) if self.stalled >= turns => Some(Outcome::Draw(Reason::Stalled)),
+            StallRule::MostUnits(turns) if self.stalled >= turns => {
+                Some(match self.players[0].len().cmp(&self.players[1].len()) {
+                    Ordering::Greater => Outcome::Win(0, Reason::Stalled),
+                    Ordering::Less => Outcome::Win(1, Reason::Stalled),
+                    Ordering::Equal => Outcome::Draw(Reason::Stalled),
+                })
+            }
+            _ => None,
         }
     }
+    /// Creates the game described by a map in the text format of the terrain module.
+    pub fn from_text(text: &str, rules: Rc<Rules>) -> Result<Self, MapError> {
+        let (map, players) = Map::parse(text, &rules)?;
+        Ok(Game::new_with_map(map, players, rules))
+    }
     /// Creates a new random game with the given dimensions and randomly populate it with
     /// The given number of units for each player.
-    /// The units generated will be symmetrically disposed on the grid.
+    /// The terrain and the units generated will be symmetrically disposed on the grid.
     pub fn new_random(
         width: usize,
         height: usize,
         units_per_player: usize,
         my_rand: &mut XorShiftRng,
     ) -> Self {
-        let mut g = Game::new(width, height, vec![]);
+        Game::new_random_with_rules(
+            width,
+            height,
+            units_per_player,
+            Rc::new(Rules::default()),
+            my_rand,
+        )
+    }
+    /// Same as *new_random* but the units are chosen among the types of the given rules.
+    pub fn new_random_with_rules(
+        width: usize,
+        height: usize,
+        units_per_player: usize,
+        rules: Rc<Rules>,
+        my_rand: &mut XorShiftRng,
+    ) -> Self {
+        let map = Map::new_random(width, height, my_rand);
+        let mut g = Game::new_with_map(map, vec![], rules);
         g.randomly_add(width, height, units_per_player, my_rand);
         g
     }
@@ -47,12 +263,14 @@ impl Game {
         for _ in 0..units {
             self.players[player].pop();
         }
+        self.rebuild();
         self
     }
 
 
     /// Randomly populate the game with units
     /// The units generated will be symmetrically disposed on the grid for each players..
+    /// No unit is put in a wall.
     pub fn randomly_add(
         &mut self,
         width: usize,
@@ -61,80 +279,275 @@ impl Game {
         my_rand: &mut XorShiftRng,
     ) {
         self.players = vec![vec![], vec![]];
+        self.rebuild();
         for _ in 0..units_per_player {
-            let mut unit = Unit::new_random(0, width / 2, height, my_rand, 0);
-            while !self.is_free(unit.pos) {
-                unit = Unit::new_random(0, width / 2, height, my_rand, 0);
+            let mut unit = Unit::new_random(0, width / 2, height, &self.rules, my_rand, 0);
+            while !self.is_free(unit.pos) || !self.map.reachable(unit.pos) {
+                unit = Unit::new_random(0, width / 2, height, &self.rules, my_rand, 0);
             }
-            self.players[1].push(unit.reverse(width, height));
+            let reversed = unit.reverse(width, height);
+            self.board.set(unit.pos, Some((0, self.players[0].len())));
+            self.board.set(reversed.pos, Some((1, self.players[1].len())));
+            self.players[1].push(reversed);
             self.players[0].push(unit);
         }
-
+        self.rebuild();
     }
     /// Returns the list of units for the given player.
-    pub fn player(&self, player: usize) -> Vec<Unit> {
-        self.players[player].clone()
+    pub fn player(&self, player: usize) -> &[Unit] {
+        &self.players[player]
+    }
+    /// Returns the rules of the game.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// Returns the terrain of the game's grid.
+    pub fn map(&self) -> &Map {
+        &self.map
     }
     /// Returns the width of the game's grid.
     pub fn width(&self) -> usize {
-        self.width
+        self.map.width()
     }
     /// Returns the height of the game's grid.
     pub fn height(&self) -> usize {
-        self.height
+        self.map.height()
     }
     /// Returns a vector of the positions already used in the game.
     pub fn positions(&self) -> Vec<Position> {
         self.players
             .iter()
-            .flat_map(|p| p.iter().map(|u| u.pos.clone()))
+            .flat_map(|p| p.iter().map(|u| u.pos))
             .collect()
 
     }
 
-    /// We consider that we can only move+shoot one unit every turn.
-    /// Returns all the possible moves for the given player.
+    /// Every unit acting may move then may shoot, the *TurnMode* of the rules telling how many
+    /// units act in a turn. The units only shoot at the units they can hurt.
+    /// Returns all the possible moves for the given player, made by the units that did not act
+    /// yet during its turn and that it can afford.
     pub fn moves(&self, player: usize) -> Vec<Action> {
         let mut mvs = vec![];
-        let positions = &self.positions();
+        let spent = match self.acting {
+            Some(p) if p == player => self.spent,
+            _ => 0,
+        };
         for unit in self.players[player].iter() {
-            let mut actions = unit.actions(self.width, self.height, &positions);
-            mvs.extend(actions);
+            let affordable = match self.rules.turn {
+                TurnMode::ActionPoints(points) => {
+                    spent + self.rules.unit(&unit.unit_type).cost <= points
+                }
+                _ => true,
+            };
+            if unit.acted || !affordable {
+                continue;
+            }
+            mvs.extend(unit.actions(&self.rules, &self.map, &self.board));
         }
         mvs
     }
+    /// Returns the possible moves for the given unit of the game.
+    pub fn actions(&self, unit: &Unit) -> Vec<Action> {
+        unit.actions(&self.rules, &self.map, &self.board)
+    }
+    /// Returns the cells the units of the given player could shoot at during their next turn,
+    /// whether there is someone or not.
+    pub fn reach(&self, player: usize) -> Vec<Position> {
+        let mut cells = vec![];
+        for action in self.moves(player) {
+            let unit = self.at(action.unit).unwrap();
+            for aim in unit.aims(&self.rules, &self.map, action.mv, &self.board) {
+                if !cells.contains(&aim) {
+                    cells.push(aim);
+                }
+            }
+        }
+        cells
+    }
     /// Returns true if the cell at the given position if empty, false otherwise.
     pub fn is_free(&self, pos: Position) -> bool {
-        return self.at(pos).is_none();
+        !self.board.occupied(pos)
     }
     /// Returns the unit at the given position.
-    pub fn at(&self, pos: Position) -> Option<Unit> {
-        for player in 0..self.players.len() {
-            for unit in 0..self.players[player].len() {
-                if self.players[player][unit].pos == pos {
-                    return Some(self.players[player][unit].clone());
+    pub fn at(&self, pos: Position) -> Option<&Unit> {
+        self.board
+            .get(pos)
+            .map(|(team, index)| &self.players[team][index])
+    }
+    /// Apply the given move to the game.
+    /// It will move the unit and shoot where asked, if asked, then end the turn of the player if
+    /// none of its units can act anymore.
+    /// The unit shot loses the damage of the shooter minus its armor, see *Rules::damage*, and
+    /// dies when it has no hit points left. Without friendly fire, the units of the shooter's
+    /// team are not hurt. The shots from afar only hit the units in cover every few shots, see
+    /// *Terrain::Cover*, and a unit leaving its cell starts again from zero. Only a unit can shoot.
+    ///
+    /// Returns what is needed to undo the move with *undo_move*.
+    pub fn apply_move(&mut self, action: &Action) -> Undo {
+        let mut undo = Undo {
+            hash: self.hash,
+            acting: self.acting,
+            spent: self.spent,
+            turns: (self.current, self.turn, self.stalled),
+            changes: vec![],
+        };
+        let shooter = self.at(action.unit).map(|u| (u.team, u.unit_type));
+        if let (Some(shooter), Some(aim)) = (shooter, action.shoot) {
+            self.shoot(shooter, action.mv, aim, &mut undo.changes);
+        }
+        if let Some((team, index)) = self.board.get(action.unit) {
+            let mv = action.mv;
+            self.update(team, index, &mut undo.changes, |unit| {
+                if unit.pos != mv {
+                    unit.grazed = 0;
                 }
+                unit.pos = mv;
+                unit.acted = true;
+            });
+        }
+        if let Some((team, unit_type)) = shooter {
+            if self.acting.is_some_and(|p| p != team) {
+                self.reset_turn(&mut undo.changes);
+            }
+            let spent = self.spent + self.rules.unit(&unit_type).cost;
+            self.set_turn(Some(team), spent);
+            if self.rules.turn == TurnMode::Single || self.moves(team).is_empty() {
+                self.reset_turn(&mut undo.changes);
             }
         }
-        None
+        undo
     }
-    /// Apply the given move to the game.
-    /// It will move the unit and shoot where asked.
-    pub fn apply_move(&mut self, action: &Action) {
-        for player in 0..self.players.len() {
-            for unit in 0..self.players[player].len() {
-                if self.players[player][unit].pos == action.shoot {
-                    self.players[player].remove(unit);
-                    break;
+    /// Undoes the last move applied, given what *apply_move* returned.
+    pub fn undo_move(&mut self, undo: Undo) {
+        for change in undo.changes.into_iter().rev() {
+            match change {
+                Change::Updated(team, index, unit) => {
+                    self.update(team, index, &mut vec![], |u| *u = unit);
+                }
+                Change::Removed(team, index, unit) => {
+                    self.players[team].insert(index, unit);
+                    self.reindex(team, index);
                 }
             }
         }
-        for player in 0..self.players.len() {
-            for unit in 0..self.players[player].len() {
-                if self.players[player][unit].pos == action.unit {
-                    self.players[player][unit].pos = action.mv;
+        self.acting = undo.acting;
+        self.spent = undo.spent;
+        let (current, turn, stalled) = undo.turns;
+        self.current = current;
+        self.turn = turn;
+        self.stalled = stalled;
+        self.hash = undo.hash;
+    }
+    /// Shoots from the given position at the aimed cell, the shooter being the team and the type
+    /// of the unit shooting.
+    fn shoot(
+        &mut self,
+        shooter: (usize, UnitType),
+        from: Position,
+        aim: Position,
+        changes: &mut Vec<Change>,
+    ) {
+        let (team, index) = match self.board.get(aim) {
+            Some(occupant) => occupant,
+            None => return,
+        };
+        let (shooter_team, shooter_type) = shooter;
+        let target_type = self.players[team][index].unit_type;
+        if shooter_team == self.players[team][index].team && !self.rules.friendly_fire {
+            return;
+        }
+        let covered = self.map.at(aim) == Terrain::Cover && from.distance(aim) > 1;
+        let (damage, cover) = (self.rules.damage(&shooter_type, &target_type), self.rules.cover);
+        self.update(team, index, changes, |target| {
+            if covered {
+                target.grazed += cover;
+                if target.grazed < 100 {
+                    return;
                 }
+                target.grazed -= 100;
             }
+            target.hp = target.hp.saturating_sub(damage);
+        });
+        if self.players[team][index].hp == 0 {
+            let unit = self.players[team].remove(index);
+            self.hash ^= self.unit_key(&unit);
+            self.board.set(unit.pos, None);
+            self.reindex(team, index);
+            self.set_stalled(0);
+            changes.push(Change::Removed(team, index, unit));
+        }
+    }
+    /// Changes the unit at the given team and index, keeping the board and the hash up to date
+    /// and recording the unit as it was.
+    fn update<F: FnOnce(&mut Unit)>(
+        &mut self,
+        team: usize,
+        index: usize,
+        changes: &mut Vec<Change>,
+        change: F,
+    ) {
+        let old = self.players[team][index].clone();
+        change(&mut self.players[team][index]);
+        let new = &self.players[team][index];
+        self.hash ^= self.unit_key(&old) ^ self.unit_key(new);
+        if old.pos != new.pos {
+            self.board.set(old.pos, None);
+            self.board.set(new.pos, Some((team, index)));
+        }
+        changes.push(Change::Updated(team, index, old));
+    }
+    /// Updates the board for the units of the team from the given index, after an unit was
+    /// removed or inserted.
+    fn reindex(&mut self, team: usize, from: usize) {
+        for index in from..self.players[team].len() {
+            self.board.set(self.players[team][index].pos, Some((team, index)));
+        }
+    }
+    /// Sets the turn in progress, keeping the hash up to date.
+    /// The current player is the acting one, or the next one once its turn ended.
+    fn set_turn(&mut self, acting: Option<usize>, spent: u32) {
+        self.hash ^= self.turn_key();
+        self.current = match (self.acting, acting) {
+            (_, Some(p)) => p,
+            (Some(p), None) => 1 - p,
+            (None, None) => self.current,
+        };
+        self.acting = acting;
+        self.spent = spent;
+        self.hash ^= self.turn_key();
+    }
+    /// Applies all the actions of the turn.
+    pub fn apply_turn(&mut self, turn: &Turn) {
+        for action in turn.actions.iter() {
+            self.apply_move(action);
+        }
+    }
+    /// Ends the turn in progress, the units of the player can act again during its next turn.
+    pub fn end_turn(&mut self) {
+        self.reset_turn(&mut vec![]);
+    }
+    /// Ends the turn in progress, recording the units that can act again.
+    fn reset_turn(&mut self, changes: &mut Vec<Change>) {
+        for team in 0..self.players.len() {
+            for index in 0..self.players[team].len() {
+                if self.players[team][index].acted {
+                    self.update(team, index, changes, |unit| unit.acted = false);
+                }
+            }
+        }
+        if self.acting.is_some() {
+            let stalled = self.stalled + 1;
+            self.turn += 1;
+            self.set_stalled(stalled);
+        }
+        self.set_turn(None, 0);
+    }
+    /// Returns the player playing after an action of the given player : the same one while its
+    /// turn goes on, the other one afterwards.
+    pub fn next_player(&self, player: usize) -> usize {
+        match self.acting {
+            Some(p) if p == player => player,
+            _ => 1 - player,
         }
     }
 }
@@ -143,8 +556,8 @@ impl fmt::Display for Game {
     /// Formats the game to a user readable format.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut s = "".to_string();
-        for x in 0..self.width {
-            for y in 0..self.height {
+        for x in 0..self.width() {
+            for y in 0..self.height() {
                 let mut someone = 0;
                 for player in 0..self.players.len() {
                     for unit in 0..self.players[player].len() {
@@ -157,7 +570,7 @@ impl fmt::Display for Game {
                 }
                 s.push_str(&format!("|{}|", someone));
             }
-            s.push_str(&format!("\n"));
+            s.push('\n');
         }
         write!(f, "{}", s)
     }
@@ -169,7 +582,11 @@ impl fmt::Display for Game {
 #[cfg(test)]
 mod test {
     use super::*;
-    use unit::{Position, UnitType};
+    use ai::AI;
+    use evaluator::{AliveUnitsEvaluator, Evaluator};
+    use randomai::RandomAI;
+    use terrain::{Map, Terrain};
+    use unit::{Action, Position, UnitType};
     /// Just checks that the right number of moves are generated.
     #[test]
     fn test_nb_moves() {
@@ -179,12 +596,244 @@ mod test {
             vec![
                 vec![],
                 vec![
-                    Unit::new(Position::new(0, 0), UnitType::Gunner, 1),
-                    Unit::new(Position::new(9, 9), UnitType::MobileTower, 1),
+                    Unit::new(Position::new(0, 0), UnitType::GUNNER, 1),
+                    Unit::new(Position::new(9, 9), UnitType::MOBILE_TOWER, 1),
                 ],
             ],
         );
         assert_eq!(game.moves(0).len(), 0);
-        assert_eq!(game.moves(1).len(), 20);
+        assert_eq!(game.moves(1).len(), 6);
+    }
+    /// The knights need two shots to die, the armor of the wall absorbs a hit point per shot.
+    #[test]
+    fn test_damage() {
+        let text = "[Knight]
+                    hit_points = 2
+                    moves = 0,0
+                    shoots = cross:1
+                    [Wall]
+                    hit_points = 2
+                    armor = 1
+                    moves = 0,0";
+        let rules = Rc::new(Rules::parse(text).unwrap());
+        let mut game = Game::new_with_rules(
+            10,
+            10,
+            vec![
+                vec![Unit::new(Position::new(0, 0), UnitType(0), 0)],
+                vec![
+                    Unit::new(Position::new(0, 1), UnitType(0), 1),
+                    Unit::new(Position::new(1, 0), UnitType(1), 1),
+                ],
+            ],
+            rules,
+        );
+        let mut eval = AliveUnitsEvaluator::with_values(vec![3, 1]);
+        eval.count_hit_points();
+        assert_eq!(eval.eval(&game, 1), 8);
+        let shoot_knight = Action::new(Position::new(0, 0), (0, 0), (0, 1));
+        game.apply_move(&shoot_knight);
+        assert_eq!(game.at(Position::new(0, 1)).unwrap().hp, 1);
+        assert_eq!(eval.eval(&game, 1), 5);
+        game.apply_move(&shoot_knight);
+        assert!(game.at(Position::new(0, 1)).is_none());
+        game.apply_move(&Action::new(Position::new(0, 0), (0, 0), (1, 0)));
+        assert_eq!(game.at(Position::new(1, 0)).unwrap().hp, 1);
+        assert_eq!(AliveUnitsEvaluator::with_values(vec![3, 1]).eval(&game, 1), 1);
+    }
+    /// The walls block the moves and the shots, the water slows, the high ground extends the
+    /// range and the cover halves the hits of the shots from afar, not those from the adjacent
+    /// cells.
+    #[test]
+    fn test_terrain() {
+        let mut map = Map::new(10, 10);
+        map.set(Position::new(0, 1), Terrain::Wall)
+            .set(Position::new(5, 5), Terrain::Water)
+            .set(Position::new(8, 1), Terrain::HighGround)
+            .set(Position::new(2, 5), Terrain::Cover)
+            .set(Position::new(6, 6), Terrain::Cover);
+        let infantry = Unit::new(Position::new(0, 0), UnitType::INFANTRYMAN, 0);
+        let swimmer = Unit::new(Position::new(5, 5), UnitType::INFANTRYMAN, 0);
+        let gunner = Unit::new(Position::new(8, 0), UnitType::GUNNER, 0);
+        let far = Unit::new(Position::new(2, 7), UnitType::GUNNER, 0);
+        let near = Unit::new(Position::new(6, 7), UnitType::INFANTRYMAN, 0);
+        let covered = Unit::new(Position::new(2, 5), UnitType::INFANTRYMAN, 1);
+        let covered_near = Unit::new(Position::new(6, 6), UnitType::INFANTRYMAN, 1);
+        let mut game = Game::new_with_map(
+            map,
+            vec![
+                vec![infantry.clone(), swimmer.clone(), gunner.clone(), far.clone(), near.clone()],
+                vec![covered.clone(), covered_near.clone()],
+            ],
+            Rc::new(Rules::default()),
+        );
+        let actions = game.actions(&infantry);
+        let wall = Position::new(0, 1);
+        assert!(actions.iter().all(|a| a.mv != wall));
+        assert!(actions.iter().any(|a| a.mv == Position::new(0, 2)));
+        assert!(game.actions(&swimmer).iter().all(|a| a.mv.distance(swimmer.pos) <= 1));
+        let shots_from = |mv: Position| gunner.aims(game.rules(), game.map(), mv, &game.board);
+        assert_eq!(shots_from(Position::new(7, 0)).len(), 6);
+        let high_shots = shots_from(Position::new(8, 1));
+        assert_eq!(high_shots.len(), 8);
+        assert!(high_shots.contains(&Position::new(8, 4)) && high_shots.contains(&Position::new(5, 1)));
+
+        // The gunner two cells away misses its first shot and hits the second one, unless the
+        // unit in cover moved in between.
+        let far_shot = Action::new(far.pos, (0, 0), (0, -2));
+        game.apply_move(&far_shot);
+        assert_eq!(game.at(covered.pos).map(|u| (u.hp, u.grazed)), Some((1, 50)));
+        game.apply_move(&Action::hold(covered.pos));
+        assert_eq!(game.at(covered.pos).map(|u| u.grazed), Some(50));
+        game.apply_move(&Action::moving(covered.pos, (1, 0)));
+        game.apply_move(&Action::moving(Position::new(3, 5), (-1, 0)));
+        assert_eq!(game.at(covered.pos).map(|u| u.grazed), Some(0));
+        game.apply_move(&far_shot);
+        assert_eq!(game.at(covered.pos).map(|u| (u.hp, u.grazed)), Some((1, 50)));
+        game.apply_move(&far_shot);
+        assert!(game.at(covered.pos).is_none());
+        // The adjacent infantryman always hits.
+        game.apply_move(&Action::new(near.pos, (0, 0), (0, -1)));
+        assert!(game.player(1).is_empty());
+        // Nobody shoots from an empty cell.
+        let mut game = Game::new(10, 10, vec![vec![], vec![covered.clone()]]);
+        game.apply_move(&Action::new(Position::new(2, 6), (0, 0), (0, -1)));
+        assert_eq!(game.player(1).len(), 1);
+    }
+    /// Every unit acts once per turn, or as many as the action points allow.
+    #[test]
+    fn test_turns() {
+        let players = vec![
+            vec![
+                Unit::new(Position::new(0, 0), UnitType::GUNNER, 0),
+                Unit::new(Position::new(5, 0), UnitType::INFANTRYMAN, 0),
+            ],
+            vec![Unit::new(Position::new(9, 9), UnitType::GUNNER, 1)],
+        ];
+        let mut rules = Rules::default();
+        rules.turn = TurnMode::AllUnits;
+        let mut game = Game::new_with_rules(10, 10, players.clone(), Rc::new(rules.clone()));
+        let first = game.moves(0)[0].clone();
+        game.apply_move(&first);
+        assert_eq!(game.next_player(0), 0);
+        assert!(game.moves(0).iter().all(|a| a.unit == Position::new(5, 0)));
+        assert_eq!(game.moves(1).len(), Game::new(10, 10, players.clone()).moves(1).len());
+        let second = game.moves(0)[0].clone();
+        game.apply_move(&second);
+        assert_eq!(game.next_player(0), 1);
+        assert!(game.player(0).iter().all(|u| !u.acted));
+
+        let game = Game::new_with_rules(10, 10, players.clone(), Rc::new(rules.clone()));
+        assert_eq!(RandomAI::new().play_turn(&game, 0).actions.len(), 2);
+
+        rules.turn = TurnMode::ActionPoints(3);
+        rules.units[UnitType::GUNNER.0].cost = 2;
+        let game = Game::new_with_rules(10, 10, players.clone(), Rc::new(rules.clone()));
+        assert_eq!(RandomAI::new().play_turn(&game, 0).actions.len(), 2);
+        rules.units[UnitType::INFANTRYMAN.0].cost = 2;
+        let game = Game::new_with_rules(10, 10, players, Rc::new(rules));
+        assert_eq!(RandomAI::new().play_turn(&game, 0).actions.len(), 1);
+    }
+    /// The units may move without shooting, shoot without moving or hold, and only aim at their
+    /// allies with friendly fire.
+    #[test]
+    fn test_action_kinds() {
+        let gunner = Unit::new(Position::new(0, 0), UnitType::GUNNER, 0);
+        let players = vec![
+            vec![gunner.clone(), Unit::new(Position::new(0, 1), UnitType::INFANTRYMAN, 0)],
+            vec![Unit::new(Position::new(2, 0), UnitType::GUNNER, 1)],
+        ];
+        let mut rules = Rules::default();
+        let game = Game::new_with_rules(10, 10, players.clone(), Rc::new(rules.clone()));
+        let aims = |game: &Game| {
+            game.actions(&gunner)
+                .into_iter()
+                .filter(|a| a.mv == gunner.pos)
+                .filter_map(|a| a.shoot)
+                .collect::<Vec<Position>>()
+        };
+        assert_eq!(aims(&game), vec![Position::new(0, 1), Position::new(2, 0)]);
+        rules.friendly_fire = false;
+        let mut game = Game::new_with_rules(10, 10, players, Rc::new(rules));
+        assert_eq!(aims(&game), vec![Position::new(2, 0)]);
+
+        game.apply_move(&Action::hold(gunner.pos));
+        assert_eq!(game.at(gunner.pos), Some(&Unit { acted: false, ..gunner.clone() }));
+        game.apply_move(&Action::moving(Position::new(2, 0), (-1, 0)));
+        assert_eq!(game.player(0).len(), 2);
+        game.apply_move(&Action::new(gunner.pos, (0, 0), (1, 0)));
+        assert_eq!(game.at(gunner.pos).map(|u| u.pos), Some(gunner.pos));
+        assert!(game.player(1).is_empty());
+    }
+    /// The game ends when a player has no unit left, or after the turns of the stall rule.
+    #[test]
+    fn test_outcome() {
+        let players = vec![
+            vec![
+                Unit::new(Position::new(0, 0), UnitType::GUNNER, 0),
+                Unit::new(Position::new(9, 0), UnitType::GUNNER, 0),
+            ],
+            vec![Unit::new(Position::new(0, 9), UnitType::GUNNER, 1)],
+        ];
+        let mut game = Game::new(10, 10, players);
+        game.set_stall(StallRule::MostUnits(2));
+        let start = game.clone();
+        assert_eq!((game.outcome(), game.current_player(), game.turn()), (None, 0, 0));
+        game.apply_move(&Action::hold(Position::new(0, 0)));
+        assert_eq!((game.outcome(), game.current_player(), game.turn()), (None, 1, 1));
+        game.apply_move(&Action::hold(Position::new(0, 9)));
+        // The units are back as they were, but the game is closer to its end.
+        assert!(game.zobrist() != start.zobrist());
+        let mut fresh = game.clone();
+        fresh.rebuild();
+        assert_eq!(fresh.zobrist(), game.zobrist());
+        assert_eq!(game.outcome(), Some(Outcome::Win(0, Reason::Stalled)));
+        game.set_stall(StallRule::Draw(3));
+        assert_eq!(game.outcome(), None);
+        game.apply_move(&Action::moving(Position::new(0, 0), (0, 1)));
+        assert_eq!(game.outcome(), Some(Outcome::Draw(Reason::Stalled)));
+
+        game.set_stall(StallRule::Never);
+        game.apply_move(&Action::moving(Position::new(0, 9), (0, -1)));
+        game.apply_move(&Action::moving(Position::new(0, 1), (0, 1)));
+        game.apply_move(&Action::moving(Position::new(0, 8), (0, -1)));
+        game.apply_move(&Action::moving(Position::new(0, 2), (0, 1)));
+        game.apply_move(&Action::moving(Position::new(0, 7), (0, -1)));
+        assert_eq!(game.outcome(), None);
+        game.apply_move(&Action::new(Position::new(0, 3), (0, 1), (0, 3)));
+        assert_eq!(game.outcome(), Some(Outcome::Win(0, Reason::Eliminated)));
+        assert_eq!(game.turn(), 9);
+    }
+    /// Undoing the moves gives back the game as it was, and the hash kept up to date is the hash
+    /// computed from scratch.
+    #[test]
+    fn test_undo() {
+        let mut rules = Rules::default();
+        rules.turn = TurnMode::AllUnits;
+        rules.stall = StallRule::Draw(50);
+        let mut my_rand = XorShiftRng::new_unseeded();
+        let mut game = Game::new_random_with_rules(10, 10, 4, Rc::new(rules), &mut my_rand);
+        let mut player = 0;
+        let mut history = vec![];
+        for _ in 0..30 {
+            let moves = game.moves(player);
+            if moves.is_empty() {
+                break;
+            }
+            // Shooting when possible, so that units get hurt and die.
+            let action = moves.iter().rev().find(|a| a.shoot.is_some()).unwrap_or(&moves[0]);
+            let before = game.clone();
+            let undo = game.apply_move(action);
+            let mut fresh = game.clone();
+            fresh.rebuild();
+            assert_eq!(fresh, game);
+            history.push((before, undo));
+            player = game.next_player(player);
+        }
+        assert!(game.player(0).len() + game.player(1).len() < 8);
+        while let Some((before, undo)) = history.pop() {
+            game.undo_move(undo);
+            assert_eq!(game, before);
+        }
     }
 }
@@ -18,3 +18,6 @@ pub mod minmax;
 pub mod montecarlo;
 pub mod evaluator;
 pub mod comparator;
+pub mod rules;
+pub mod terrain;
+pub mod board;
@@ -9,6 +9,8 @@
 //! The crate also contain an Arena struct in wich we can test two ai against each other on a lot of
 //! games.
 extern crate rand;
+use std::env;
+use std::process;
 mod game;
 mod ai;
 mod unit;
@@ -18,18 +20,32 @@ mod minmax;
 mod montecarlo;
 mod evaluator;
 mod comparator;
+mod rules;
+mod terrain;
+mod board;
 
 use ai::*;
 use naive::NaiveAI;
 use minmax::NegaMaxAI;
 use montecarlo::MonteCarloAI;
 use comparator::{get_all_naives, unit_eval_double};
+use rules::Rules;
 
 /// The main function.
 /// Here we launch some fight between ais to compare them.
+///
+/// Launching it with a rules file as argument will play the games with these rules instead of the
+/// classic ones, see the rules module for the format.
 pub fn main() {
     let arena = &mut Arena::new(10, 15, 6, false, 100);
     arena.progression(4, 1); // every 3 games, favorise a player by one unit.
+    if let Some(path) = env::args().nth(1) {
+        let rules = Rules::load(&path).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(2);
+        });
+        arena.rules(rules);
+    }
 
 
 
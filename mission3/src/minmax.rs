@@ -1,11 +1,48 @@
 //! The minmax ai
 //! This module uses a variant called negamax where we consider
 //! that our score is always the opposite of the ennemy's score.
+//!
+//! The tree is searched with the alpha-beta pruning in its principal variation search form : the
+//! first move is searched with the whole window, the others with a null window to prove they are
+//! not better, and searched again if they are. The pruning being best when the best moves come
+//! first, the shots are tried first, then the killer moves wich made a cut at the same depth,
+//! then the moves by their history of cuts.
+//!
+//! The scores of the positions already searched are kept in a transposition table, keyed by the
+//! Zobrist hash of the game, so that a position reached by different orders of the moves is only
+//! searched once. The ai searches deeper and deeper until its depth or its budget is reached,
+//! the best move of each search being tried first by the next one.
+use std::collections::HashMap;
+use std::time::Instant;
 use game::Game;
 use unit::Action;
-use ai::AI;
+use ai::{AI, Budget};
 use evaluator::Evaluator;
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// What the score of a position in the transposition table is.
+enum Bound {
+    /// The exact score.
+    Exact,
+    /// The score is at least this one, the search having been cut.
+    Lower,
+    /// The score is at most this one, no move having reached alpha.
+    Upper,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// A position searched, in the transposition table.
+struct Entry {
+    /// The depth of the search.
+    depth: usize,
+    /// The score found.
+    score: i32,
+    /// What the score is.
+    bound: Bound,
+    /// The best move found.
+    best: Option<Action>,
+}
+
 /// The negamax ai.
 /// will use the minmax algorithm.
 pub struct NegaMaxAI {
@@ -13,47 +50,361 @@ pub struct NegaMaxAI {
     depth: usize,
     /// The evaluator used.
     evaluator: Box<Evaluator>,
+    /// True if the tree is searched with the alpha-beta pruning.
+    pruning: bool,
+    /// The number of nodes visited during the last search.
+    nodes: usize,
+    /// The two last moves without shot wich made a cut, for every depth.
+    killers: Vec<[Option<Action>; 2]>,
+    /// The cuts made by the moves, weighted by the depth left.
+    history: HashMap<Action, usize>,
+    /// The positions searched, by their Zobrist hash.
+    table: HashMap<u64, Entry>,
+    /// The budget of the moves.
+    budget: Budget,
+    /// The time at wich the search in progress must stop.
+    deadline: Option<Instant>,
+    /// The number of nodes after wich the search in progress must stop.
+    max_nodes: usize,
+    /// True if the search in progress was stopped by its budget.
+    stopped: bool,
 }
 impl NegaMaxAI {
     /// Creates a new ai with the given evaluator.
     pub fn new(depth: usize, evaluator: Box<Evaluator>) -> Self {
-        NegaMaxAI { depth, evaluator }
+        NegaMaxAI {
+            depth,
+            evaluator,
+            pruning: true,
+            nodes: 0,
+            killers: vec![],
+            history: HashMap::new(),
+            table: HashMap::new(),
+            budget: Budget::Default,
+            deadline: None,
+            max_nodes: usize::MAX,
+            stopped: false,
+        }
+    }
+    /// Sets whether the tree is searched with the alpha-beta pruning, or entirely.
+    /// Both searches give the same scores, the pruning visiting far less nodes.
+    pub fn pruning(&mut self, pruning: bool) -> &mut Self {
+        self.pruning = pruning;
+        self
+    }
+    /// Returns the number of nodes visited during the last search.
+    pub fn nodes(&self) -> usize {
+        self.nodes
     }
     /// Evaluates the state of the game for the given player.
     /// The evaluation is made with the min max algorithm.
     /// But because we consider that the ennemy score is the opposite of our score.
     /// we can always take the max value and negate it before returning it.
-    pub fn eval(&self, game: &Game, player: usize, depth: usize) -> i32 {
-        if depth == 0 || game.player(player).len() == 0 {
+    ///
+    /// The depth counts the actions, not the turns : when the turn of the player goes on after
+    /// an action, the player searches its next action and the score is not negated.
+    /// The moves are applied to the game then undone, the game being left as it was.
+    ///
+    /// If the budget is spent, the search stops and the score returned is meaningless.
+    pub fn eval(&mut self, game: &mut Game, player: usize, depth: usize) -> i32 {
+        self.nodes += 1;
+        if self.spent() {
+            0
+        } else if depth == 0 || game.outcome().is_some() {
             self.evaluator.eval(game, player)
         } else {
-            let moves = game.moves(player);
-            moves
-                .iter()
-                .map(|mv| {
-                    let mut g = game.clone();
-                    g.apply_move(mv);
-                    self.eval(&g, 1 - player, depth - 1) * -1
-                })
-                .max()
-                .unwrap()
+            let mut best = -i32::MAX;
+            for mv in game.moves(player) {
+                let undo = game.apply_move(&mv);
+                let score = self.eval_after(game, player, depth - 1);
+                game.undo_move(undo);
+                if self.stopped {
+                    break;
+                }
+                best = best.max(score);
+            }
+            best
+        }
+    }
+    /// Returns true if the budget of the search in progress is spent, wich stops it.
+    fn spent(&mut self) -> bool {
+        if self.nodes.is_multiple_of(256) && self.deadline.is_some_and(|d| Instant::now() >= d) {
+            self.stopped = true;
         }
+        if self.nodes >= self.max_nodes {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+    /// Evaluates the game for the player who just made an action, from the point of view of the
+    /// next player.
+    fn eval_after(&mut self, game: &mut Game, player: usize, depth: usize) -> i32 {
+        let next = game.next_player(player);
+        match next == player {
+            true => self.eval(game, player, depth),
+            false => -self.eval(game, next, depth),
+        }
+    }
+    /// Same as *eval* with the alpha-beta pruning : the score is exact if it is between alpha
+    /// and beta, else it is only a bound of the exact score on the same side of the window.
+    /// The ply is the number of actions made since the root of the search.
+    ///
+    /// If the budget is spent, the search stops and the score returned is meaningless.
+    pub fn alpha_beta(
+        &mut self,
+        game: &mut Game,
+        player: usize,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.spent() {
+            return 0;
+        }
+        if depth == 0 || game.outcome().is_some() {
+            return self.evaluator.eval(game, player);
+        }
+        let key = game.zobrist();
+        let mut hint = None;
+        if let Some(entry) = self.table.get(&key) {
+            if entry.depth >= depth {
+                match entry.bound {
+                    Bound::Exact => return entry.score,
+                    Bound::Lower if entry.score >= beta => return entry.score,
+                    Bound::Upper if entry.score <= alpha => return entry.score,
+                    _ => (),
+                }
+            }
+            hint = entry.best.clone();
+        }
+        let mut moves = self.ordered_moves(game, player, ply);
+        if let Some(index) = hint.and_then(|h| moves.iter().position(|mv| *mv == h)) {
+            let mv = moves.remove(index);
+            moves.insert(0, mv);
+        }
+        let (start, mut best, mut best_move) = (alpha, -i32::MAX, None);
+        for (i, mv) in moves.iter().enumerate() {
+            let undo = game.apply_move(mv);
+            let mut score = match i {
+                0 => self.alpha_beta_after(game, player, depth - 1, alpha, beta, ply + 1),
+                _ => self.alpha_beta_after(game, player, depth - 1, alpha, alpha + 1, ply + 1),
+            };
+            if i > 0 && score > alpha && score < beta {
+                score = self.alpha_beta_after(game, player, depth - 1, score, beta, ply + 1);
+            }
+            game.undo_move(undo);
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv.clone());
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                self.record_cut(mv, depth, ply);
+                break;
+            }
+        }
+        let bound = match best {
+            _ if best <= start => Bound::Upper,
+            _ if best >= beta => Bound::Lower,
+            _ => Bound::Exact,
+        };
+        let entry = Entry {
+            depth,
+            score: best,
+            bound,
+            best: best_move,
+        };
+        self.table.insert(key, entry);
+        best
+    }
+    /// Same as *eval_after* with the alpha-beta pruning, the window being the one of the player
+    /// who just made an action.
+    fn alpha_beta_after(
+        &mut self,
+        game: &mut Game,
+        player: usize,
+        depth: usize,
+        alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        let next = game.next_player(player);
+        match next == player {
+            true => self.alpha_beta(game, player, depth, alpha, beta, ply),
+            false => -self.alpha_beta(game, next, depth, -beta, -alpha, ply),
+        }
+    }
+    /// Returns the moves of the player, the most promising first : the shots, the killer moves
+    /// of the ply, then the other moves by their history.
+    fn ordered_moves(&self, game: &Game, player: usize, ply: usize) -> Vec<Action> {
+        let mut moves = game.moves(player);
+        let killers = self.killers.get(ply);
+        moves.sort_by_key(|mv| {
+            let killer = killers.is_some_and(|k| k.contains(&Some(mv.clone())));
+            let rank = match mv.shoot {
+                Some(_) => 0,
+                None if killer => 1,
+                None => 2,
+            };
+            (rank, -(*self.history.get(mv).unwrap_or(&0) as i64))
+        });
+        moves
+    }
+    /// Remembers the move wich made a cut : as a killer move of the ply if it does not shoot,
+    /// the shots being tried first anyway, and in the history.
+    fn record_cut(&mut self, mv: &Action, depth: usize, ply: usize) {
+        if mv.shoot.is_none() {
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None, None]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0].as_ref() != Some(mv) {
+                killers[1] = killers[0].take();
+                killers[0] = Some(mv.clone());
+            }
+        }
+        *self.history.entry(mv.clone()).or_insert(0) += depth * depth;
+    }
+    /// Returns the best move of the player and its score, searching the tree with or without
+    /// pruning.
+    ///
+    /// The tree is searched deeper and deeper until the depth of the ai is reached, or as deep as
+    /// the budget allows : the best move of the deepest search completed is played, the first
+    /// search being always completed. Without the pruning nor a budget, the ai searches its depth
+    /// at once. With a number of iterations, the ai
+    /// searches that many depths.
+    pub fn best_move(&mut self, game: &Game, player: usize) -> (Action, i32) {
+        let mut game = game.clone();
+        self.nodes = 0;
+        self.killers.clear();
+        self.history.clear();
+        self.table.clear();
+        self.stopped = false;
+        self.deadline = None;
+        self.max_nodes = usize::MAX;
+        let deadline = self.budget.deadline();
+        let depth = match self.budget {
+            Budget::Default => self.depth,
+            Budget::Iterations(n) => n,
+            Budget::Time(_) | Budget::Nodes(_) => usize::MAX - 1,
+        };
+        let mut best = self.search_root(&mut game, player, 0, None).unwrap();
+        self.deadline = deadline;
+        if let Budget::Nodes(n) = self.budget {
+            self.max_nodes = n;
+        }
+        // Without a budget, the whole search goes straight to the depth of the ai.
+        let first = match (self.pruning, self.budget) {
+            (false, Budget::Default) => depth,
+            _ => 1,
+        };
+        for depth in first..depth + 1 {
+            match self.search_root(&mut game, player, depth, Some(best.0.clone())) {
+                Some(found) => best = found,
+                None => break,
+            }
+        }
+        best
+    }
+    /// Searches the moves of the player at the given depth, the hinted one first, and returns
+    /// the best one with its score, or None if the deadline passed.
+    fn search_root(
+        &mut self,
+        game: &mut Game,
+        player: usize,
+        depth: usize,
+        hint: Option<Action>,
+    ) -> Option<(Action, i32)> {
+        let mut moves = match self.pruning {
+            true => self.ordered_moves(game, player, 0),
+            false => game.moves(player),
+        };
+        if let Some(index) = hint.and_then(|h| moves.iter().position(|mv| *mv == h)) {
+            let mv = moves.remove(index);
+            moves.insert(0, mv);
+        }
+        let mut alpha = -i32::MAX;
+        let mut best = None;
+        for mv in moves {
+            let undo = game.apply_move(&mv);
+            let score = match self.pruning {
+                true => self.alpha_beta_after(game, player, depth, alpha, i32::MAX, 1),
+                false => self.eval_after(game, player, depth),
+            };
+            game.undo_move(undo);
+            if self.stopped {
+                return None;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(mv);
+            }
+        }
+        best.map(|mv| (mv, alpha))
     }
 }
 impl AI for NegaMaxAI {
     /// Makes a choice using the min max algorithm.
     fn play(&mut self, game: &Game, player: usize) -> Action {
-        let moves = game.moves(player);
-        moves
-            .iter()
-            .map(|mv| {
-                let mut g = game.clone();
-                g.apply_move(mv);
-                (mv, self.eval(&g, 1 - player, self.depth) * -1)
-            })
-            .max_by_key(|x| x.1)
-            .unwrap()
-            .0
-            .clone()
+        self.best_move(game, player).0
+    }
+    fn budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use evaluator::AliveUnitsEvaluator;
+    use rand::XorShiftRng;
+    use std::time::Duration;
+
+    /// The pruning finds the moves of the same score as the whole search, visiting less nodes.
+    #[test]
+    fn test_pruning() {
+        let mut my_rand = XorShiftRng::new_unseeded();
+        for _ in 0..3 {
+            let game = Game::new_random(8, 8, 3, &mut my_rand);
+            let mut full = NegaMaxAI::new(3, Box::new(AliveUnitsEvaluator::new(2, 1, 3)));
+            full.pruning(false);
+            let mut pruned = NegaMaxAI::new(3, Box::new(AliveUnitsEvaluator::new(2, 1, 3)));
+            let (_, score) = full.best_move(&game, 0);
+            let (mv, pruned_score) = pruned.best_move(&game, 0);
+            assert_eq!(score, pruned_score);
+            let mut played = game.clone();
+            played.apply_move(&mv);
+            let next = played.next_player(0);
+            let depth = full.depth;
+            let mv_score = match next {
+                0 => full.eval(&mut played, 0, depth),
+                _ => -full.eval(&mut played, next, depth),
+            };
+            assert_eq!(mv_score, score);
+            assert!(pruned.nodes() < full.nodes() / 2);
+        }
+    }
+    /// With a budget, the ai plays in time or within its nodes whatever its depth.
+    #[test]
+    fn test_budget() {
+        let game = Game::new_random(10, 10, 5, &mut XorShiftRng::new_unseeded());
+        let mut ai = NegaMaxAI::new(1, Box::new(AliveUnitsEvaluator::new(2, 1, 3)));
+        ai.budget(Budget::Time(Duration::from_millis(100)));
+        let start = Instant::now();
+        let mv = ai.play(&game, 0);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(game.moves(0).contains(&mv));
+        ai.budget(Budget::Nodes(5_000));
+        ai.play(&game, 0);
+        let first = game.moves(0).len() + 1;
+        assert!(ai.nodes() > first && ai.nodes() <= 5_000 + first);
+        ai.pruning(false);
+        ai.play(&game, 0);
+        assert!(ai.nodes() > first && ai.nodes() <= 5_000 + first);
     }
 }
@@ -1,10 +1,19 @@
 //! The monte carlo tree search algorithm
 //!
+//! Every iteration selects a node of the tree with the UCT formula, expands one of its moves,
+//! plays a random game from there and counts its result in all the nodes on the way back to the
+//! root. The move played is the most visited one, wich is the most robust choice.
+use std::time::Instant;
 use game::Game;
 use unit::Action;
-use ai::{AI, Arena};
-use randomai::RandomAI;
+use ai::{AI, Budget};
+use rules::StallRule;
 use evaluator::Evaluator;
+use rand::{XorShiftRng, Rng};
+
+/// The number of turns without death after wich the games of the tree end, if the rules of the
+/// game do not end them, so that the random games end.
+const PLAYOUT_STALL: usize = 100;
 
 /// A node of the tree.
 pub struct Node {
@@ -12,66 +21,71 @@ pub struct Node {
     move_to: Option<Action>,
     /// The already explored sons of this node.
     sons: Vec<Node>,
-    /// The number of wins for this node (for the node)
-    wins: usize,
-    /// The number of loses for this node.
-    loses: usize,
+    /// The games won by the player who made the move to this node, a draw counting as half a win.
+    wins: f64,
+    /// The number of games played in this node or its descendants.
+    visits: usize,
     /// The state of the game for this node.
     game: Game,
     /// The next player who will make a move.
     player: usize,
+    /// The player who made the move to this node.
+    mover: usize,
     /// The possible moves at this node that were not explored.
     possible_moves: Vec<Action>,
 }
 impl Node {
     /// Creates a new node with the given game state and the given player
-    /// Also takes the move used to get to this node.
-    pub fn new(game: Game, player: usize, mv: Option<Action>) -> Self {
-        let possible_moves = game.moves(player);
+    /// Also takes the move used to get to this node, and the player who made it.
+    pub fn new(game: Game, player: usize, mv: Option<(Action, usize)>) -> Self {
+        let possible_moves = match game.outcome() {
+            Some(_) => vec![],
+            None => game.moves(player),
+        };
+        let mover = mv.as_ref().map_or(1 - player, |&(_, mover)| mover);
         Node {
-            move_to: mv,
+            move_to: mv.map(|(mv, _)| mv),
             sons: vec![],
-            wins: 0,
-            loses: 0,
-            game: game,
-            player: player,
-            possible_moves: possible_moves,
+            wins: 0.0,
+            visits: 0,
+            game,
+            player,
+            mover,
+            possible_moves,
         }
     }
-    /// Choose a node to explore among the sons using the ucb1 algorithm.
-    /// Will give a confidence interval of the score of each node.
+    /// Choose a node to explore among the sons using the UCT formula : the winning rate of the
+    /// son for the player choosing it, plus a bonus for the sons seldom explored.
+    /// The exploration constant weights the bonus.
     ///
-    /// Then will choose the node with the highest high bound.
-    pub fn ucb1_choose(&mut self, ai: &mut MonteCarloAI, played_games: usize) -> (usize, usize) {
-        let games = self.games();
-        if self.sons.is_empty() {
-            return (1, 0);
-        }
+    /// Then will choose the node with the highest bound.
+    /// Returns the winner of the game played, if any.
+    pub fn ucb1_choose(&mut self, ai: &mut MonteCarloAI) -> Option<usize> {
+        let log_games = self.games().ln();
+        let exploration = ai.exploration;
+        let uct = |son: &Node| {
+            son.weighted_score(son.mover) + exploration * (log_games / son.games()).sqrt()
+        };
         let son = self.sons
             .iter_mut()
-            .min_by_key(|son| {
-                (100_000_000.0 *
-                     (son.weighted_score() as f64 +
-                          ((2.0 * games).log(2.7) / (played_games as f64)).sqrt())) as
-                    i32
-
-            })
+            .max_by(|a, b| uct(a).partial_cmp(&uct(b)).unwrap())
             .unwrap();
-        son.selection(ai, played_games)
+        son.selection(ai)
     }
     /// Returns the number of games played in this node or its descendants.
     pub fn games(&self) -> f64 {
-        (self.loses + self.wins) as f64
+        self.visits as f64
     }
     /// Finds the best nodes in all the children using a simple evaluation.
-    pub fn find_best(&self, ai: &mut MonteCarloAI) -> Action {
-        let moves = &self.possible_moves;
+    pub fn find_best(&mut self, ai: &mut MonteCarloAI) -> Action {
+        let (moves, game, player) = (&self.possible_moves, &mut self.game, self.player);
         moves
             .iter()
             .map(|mv| {
-                let mut g = self.game.clone();
-                g.apply_move(mv);
-                (mv, ai.eval(&g, self.player))
+                let undo = game.apply_move(mv);
+                let score = ai.eval(game, player);
+                game.undo_move(undo);
+                (mv, score)
             })
             .max_by_key(|x| x.1)
             .unwrap()
@@ -79,60 +93,72 @@ impl Node {
             .clone()
     }
     /// Expands the tree.
-    /// This will explore a new node, run a random game on it and returns the result in the form
-    /// (lose, win).
-    pub fn expansion(&mut self, ai: &mut MonteCarloAI, _played_games: usize) -> (usize, usize) {
+    /// This will explore a new node, run a random game on it and returns the winner.
+    /// The player of the new node is the same one if its turn goes on after the move.
+    pub fn expansion(&mut self, ai: &mut MonteCarloAI) -> Option<usize> {
         let best = self.find_best(ai);
         let index = self.possible_moves.iter().position(|a| a == &best).unwrap();
-        let mv = self.possible_moves[index].clone();
-        self.possible_moves.remove(index);
+        let mv = self.possible_moves.remove(index);
         let mut new_game = self.game.clone();
         new_game.apply_move(&mv);
-        let mut son = Node::new(new_game, 1 - self.player, Some(mv));
+        let next = new_game.next_player(self.player);
+        let mut son = Node::new(new_game, next, Some((mv, self.player)));
         // simulation.
-        let res = son.random_play();
+        let res = son.random_play(&mut ai.rand);
         self.sons.push(son);
         res
     }
     /// If we explored all the possible children nodes, it will select one using the ucb1 algorithm
-    /// else it will explore nodes.
-    pub fn selection(&mut self, ai: &mut MonteCarloAI, played_games: usize) -> (usize, usize) {
-        let (loses, wins) = match self.possible_moves.is_empty() {
-            true => self.ucb1_choose(ai, played_games),
-            false => self.expansion(ai, played_games),
+    /// else it will explore nodes. A node where the game ended counts its outcome again.
+    /// Returns the winner of the game played, if any.
+    pub fn selection(&mut self, ai: &mut MonteCarloAI) -> Option<usize> {
+        let winner = match self.game.outcome() {
+            Some(outcome) => outcome.winner(),
+            None if self.possible_moves.is_empty() => self.ucb1_choose(ai),
+            None => self.expansion(ai),
+        };
+        self.record(winner);
+        winner
+    }
+    /// Counts a game won by the given player, or a draw.
+    fn record(&mut self, winner: Option<usize>) {
+        self.visits += 1;
+        self.wins += match winner {
+            Some(p) if p == self.mover => 1.0,
+            Some(_) => 0.0,
+            None => 0.5,
         };
-        self.loses += wins;
-        self.wins += loses;
-        (wins, loses)
-    }
-    /// Randomly plays a game and returns the result in the form (lose, win)
-    pub fn random_play(&mut self) -> (usize, usize) {
-        let won = Arena::resolve(
-            &mut self.game.clone(),
-            &mut [&mut RandomAI::new(), &mut RandomAI::new()],
-            50,
-        );
-        if won == self.player {
-            self.wins += 1;
-            (0, 1)
-        } else {
-            self.loses += 1;
-            (1, 0)
-        }
     }
-    /// Returns the winnig rate
-    /// TODO: make this a float pls (wtf ?)
-    pub fn weighted_score(&self) -> usize {
-        self.wins / (self.wins + self.loses)
+    /// Randomly plays a game and returns the winner, if any.
+    /// The game must end, the ai sets a stall rule at the root of the tree otherwise.
+    pub fn random_play(&mut self, my_rand: &mut XorShiftRng) -> Option<usize> {
+        let mut game = self.game.clone();
+        let winner = loop {
+            if let Some(outcome) = game.outcome() {
+                break outcome.winner();
+            }
+            let player = game.current_player();
+            let moves = game.moves(player);
+            game.apply_move(my_rand.choose(&moves).unwrap());
+        };
+        self.record(winner);
+        winner
+    }
+    /// Returns the winnig rate of the given player in the games played in this node.
+    pub fn weighted_score(&self, player: usize) -> f64 {
+        let rate = self.wins / self.games().max(1.0);
+        match player == self.mover {
+            true => rate,
+            false => 1.0 - rate,
+        }
     }
-    /// Returns the best node by average wins.
+    /// Returns the most visited move.
     pub fn get_best(&self) -> Action {
         self.sons
             .iter()
-            .map(|son| (son.weighted_score(), son.move_to.clone().unwrap()))
-            .min_by_key(|x| x.0)
+            .max_by_key(|son| son.visits)
+            .and_then(|son| son.move_to.clone())
             .unwrap()
-            .1
     }
 }
 
@@ -140,26 +166,97 @@ impl Node {
 pub struct MonteCarloAI {
     /// The evaluation used.
     eval: Box<Evaluator>,
+    /// The budget of the moves.
+    budget: Budget,
+    /// The weight of the exploration of the nodes seldom visited in the UCT formula.
+    exploration: f64,
+    /// The random generator of the random games.
+    rand: XorShiftRng,
 }
 impl MonteCarloAI {
     /// reates a new ai.
     pub fn new(eval: Box<Evaluator>) -> Self {
         MonteCarloAI {
             eval: eval,
+            budget: Budget::Default,
+            exploration: 2f64.sqrt(),
+            rand: XorShiftRng::new_unseeded(),
         }
     }
+    /// Sets the exploration constant of the UCT formula, the square root of 2 by default.
+    pub fn exploration(&mut self, exploration: f64) -> &mut Self {
+        self.exploration = exploration;
+        self
+    }
+    /// Returns the value of the game for the given player, according to the evaluation used.
     pub fn eval(&self, game: &Game, player: usize) -> i32 {
         self.eval.eval(game, player)
     }
 }
 impl AI for MonteCarloAI {
     /// Explore the tree and returns the result of the best action.
+    /// The tree is explored 1000 times by default. Every exploration adds a node to the tree,
+    /// a budget of nodes being a budget of iterations.
     fn play(&mut self, game: &Game, player: usize) -> Action {
         //println!("mt play");
-        let mut node = Node::new(game.clone(), player, None);
-        for i in 0..1_000 {
-            node.selection(self, i);
+        let mut root = game.clone();
+        if root.rules().stall == StallRule::Never {
+            root.set_stall(StallRule::MostUnits(PLAYOUT_STALL));
+        }
+        let mut node = Node::new(root, player, None);
+        let deadline = self.budget.deadline();
+        let iterations = match self.budget {
+            Budget::Default => 1_000,
+            Budget::Iterations(n) | Budget::Nodes(n) => n.max(1),
+            Budget::Time(_) => usize::MAX,
+        };
+        for i in 0..iterations {
+            if i > 0 && deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+            node.selection(self);
         }
         node.get_best()
     }
+    fn budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ai::Arena;
+    use comparator::unit_eval_double;
+    use randomai::RandomAI;
+    use unit::Position;
+
+    /// The statistics of the nodes are the games won by the player choosing them.
+    #[test]
+    fn test_record() {
+        let game = Game::new_random(6, 6, 2, &mut XorShiftRng::new_unseeded());
+        let mut node = Node::new(game, 1, Some((Action::hold(Position::new(0, 0)), 0)));
+        node.record(Some(0));
+        node.record(Some(0));
+        node.record(None);
+        node.record(Some(1));
+        assert_eq!(node.weighted_score(0), 0.625);
+        assert_eq!(node.weighted_score(1), 0.375);
+    }
+
+    /// The ai wins against the random ai, on both sides.
+    #[test]
+    fn test_beats_random() {
+        let mut arena = Arena::new(6, 1, 3, false, 30);
+        arena.budget(Budget::Iterations(100));
+        let mut my_rand = XorShiftRng::new_unseeded();
+        let mut mcts = MonteCarloAI::new(unit_eval_double());
+        let mut wins = (0, 0);
+        for _ in 0..4 {
+            let game = Game::new_random(6, 6, 3, &mut my_rand);
+            let res = arena.rounds(&game, &mut mcts, &mut RandomAI::new());
+            wins = (wins.0 + res.0, wins.1 + res.1);
+        }
+        assert!(wins.0 >= 7, "{:?}", wins);
+    }
 }
@@ -2,39 +2,98 @@
 //! This ai will just simulate the game one turn ahead and choose the move wich looked the best.
 //! This is equivalent to a min max algorithm of depth 1.
 //!
+use std::time::Instant;
 use game::Game;
 use unit::Action;
-use ai::AI;
+use ai::{AI, Budget};
 use evaluator::Evaluator;
 /// The naive ai struct.
 pub struct NaiveAI {
     evaluator: Box<Evaluator>,
+    /// The budget of the moves.
+    budget: Budget,
 }
 impl NaiveAI {
     /// Creates a new naive ai.
     pub fn new(eval: Box<Evaluator>) -> Self {
-        NaiveAI { evaluator: eval }
+        NaiveAI {
+            evaluator: eval,
+            budget: Budget::Default,
+        }
     }
     /// Evaluates the game's state.
     pub fn eval(&self, game: &Game, player: usize) -> i32 {
         self.evaluator.eval(game, player)
     }
 }
+/// Returns the moves of the player, the shots first, then the moves of the units in turn : the
+/// first move of every unit, then their second move and so on.
+fn ordered_moves(game: &Game, player: usize) -> Vec<Action> {
+    // The index of every move among the moves of its unit.
+    let mut ranked: Vec<(usize, Action)> = vec![];
+    for mv in game.moves(player) {
+        let index = match ranked.last() {
+            Some(&(index, ref last)) if last.unit == mv.unit => index + 1,
+            _ => 0,
+        };
+        ranked.push((index, mv));
+    }
+    ranked.sort_by_key(|&(index, ref mv)| (mv.shoot.is_none(), index));
+    ranked.into_iter().map(|(_, mv)| mv).collect()
+}
 impl AI for NaiveAI {
     /// Simulates all the possible moves, evaluate the game state and choose the move
     /// with the max score.
+    ///
+    /// Evaluating a move is both a node and an iteration of the budget : once it is spent, the
+    /// best of the moves evaluated is played. The shots are evaluated first, then the moves of
+    /// the units in turn, so that a small budget still considers every unit.
     fn play(&mut self, game: &Game, player: usize) -> Action {
-        let moves = game.moves(player);
-        moves
+        let mut game = game.clone();
+        let deadline = self.budget.deadline();
+        let limit = match self.budget {
+            Budget::Nodes(n) | Budget::Iterations(n) => n.max(1),
+            _ => usize::MAX,
+        };
+        ordered_moves(&game, player)
             .iter()
-            .map(|mv| {
-                let mut g = game.clone();
-                g.apply_move(mv);
-                (mv, self.eval(&g, player))
+            .take(limit)
+            .enumerate()
+            .take_while(|&(i, _)| i == 0 || deadline.is_none_or(|d| Instant::now() < d))
+            .map(|(_, mv)| {
+                let undo = game.apply_move(mv);
+                let score = self.eval(&game, player);
+                game.undo_move(undo);
+                (mv, score)
             })
             .max_by_key(|x| x.1)
             .unwrap()
             .0
             .clone()
     }
+    fn budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::XorShiftRng;
+
+    /// The first moves ordered belong to every unit wich can act.
+    #[test]
+    fn test_ordered_moves() {
+        let game = Game::new_random(10, 10, 5, &mut XorShiftRng::new_unseeded());
+        let moves = game.moves(0);
+        let mut units = moves.iter().map(|mv| mv.unit).collect::<Vec<_>>();
+        units.dedup();
+        let ordered = ordered_moves(&game, 0);
+        assert_eq!(ordered.len(), moves.len());
+        assert!(ordered.iter().all(|mv| moves.contains(mv)));
+        let shots = ordered.iter().take_while(|mv| mv.shoot.is_some()).count();
+        assert!(ordered[shots..].iter().all(|mv| mv.shoot.is_none()));
+        let first = &ordered[shots..shots + units.len()];
+        assert!(units.iter().all(|u| first.iter().any(|mv| mv.unit == *u)));
+    }
 }
@@ -0,0 +1,410 @@
+//! The rules of the game : the types of units and what they can do.
+//! The rules can be loaded from a text file, for example :
+//!
+//! ```text
+//! # the units can hurt their own team.
+//! friendly_fire = true
+//! # one unit acts per turn, see TurnMode.
+//! turn = single
+//! # every second shot from afar hits a unit in cover, the count starting again when it moves.
+//! cover = 50
+//!
+//! [Gunner]
+//! symbol = G
+//! hit_points = 1
+//! damage = 1
+//! armor = 0
+//! cost = 1
+//! moves = cross:1
+//! shoots = cross:2 1,1
+//! ```
+//!
+//! The offsets are separated by spaces, each being either `dx,dy` or a pattern : `cross:n` the
+//! four straight lines of length n, `diag:n` the four diagonals of length n and `star:n` both.
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+use unit::UnitType;
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+/// The rules of a type of unit.
+pub struct UnitRules {
+    /// The name of the type.
+    pub name: String,
+    /// The char representing the type on the grid.
+    pub symbol: char,
+    /// The offsets at which the unit can move.
+    pub moves: Vec<(i32, i32)>,
+    /// The offsets at which the unit can shoot, after moving.
+    pub shoots: Vec<(i32, i32)>,
+    /// The number of hit points of the unit when the game starts, it dies when they reach 0.
+    pub hit_points: u32,
+    /// The number of hit points removed by a shot of the unit.
+    pub damage: u32,
+    /// The damage ignored by the unit when it is shot.
+    pub armor: u32,
+    /// The number of action points spent when the unit acts, see *TurnMode::ActionPoints*.
+    pub cost: u32,
+}
+
+impl UnitRules {
+    /// Creates the rules of a type of unit with one hit point, dealing one damage, without armor
+    /// and costing one action point.
+    pub fn new(name: &str, symbol: char, moves: Vec<(i32, i32)>, shoots: Vec<(i32, i32)>) -> Self {
+        UnitRules {
+            name: name.to_string(),
+            symbol,
+            moves,
+            shoots,
+            hit_points: 1,
+            damage: 1,
+            armor: 0,
+            cost: 1,
+        }
+    }
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+/// The rules of a game.
+pub struct Rules {
+    /// The types of units, a *UnitType* is an index in this vector.
+    pub units: Vec<UnitRules>,
+    /// True if the shots hurt the units of the shooter's team.
+    pub friendly_fire: bool,
+    /// How many units act in a turn.
+    pub turn: TurnMode,
+    /// How the game ends when nobody dies anymore.
+    pub stall: StallRule,
+    /// The share, in percents, of the shots from a cell not adjacent to a unit in cover wich hit
+    /// it, the shots hitting at regular intervals, see *Terrain::Cover*.
+    pub cover: u32,
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// How many units of a player act in a turn.
+/// In every mode, a unit acts at most once per turn and the units act one after the other.
+pub enum TurnMode {
+    /// One unit acts per turn, written `single`.
+    Single,
+    /// Every unit of the player acts in a turn, written `all`.
+    AllUnits,
+    /// The player has the given number of action points per turn, every unit acting costing its
+    /// cost, written `points:n`.
+    ActionPoints(u32),
+}
+
+impl FromStr for TurnMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "single" => Ok(TurnMode::Single),
+            "all" => Ok(TurnMode::AllUnits),
+            _ if s.starts_with("points:") => match s["points:".len()..].parse::<u32>() {
+                Ok(points) if points > 0 => Ok(TurnMode::ActionPoints(points)),
+                _ => Err(format!("invalid action points in {}", s)),
+            },
+            _ => Err(format!("unknown turn mode {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// How a game ends after a number of turns without any unit dying, the turns of both players
+/// being counted.
+pub enum StallRule {
+    /// The game goes on, written `never`.
+    Never,
+    /// The game is a draw, written `draw:n`.
+    Draw(usize),
+    /// The player with the most units alive wins, or the game is a draw if they have as many,
+    /// written `units:n`.
+    MostUnits(usize),
+}
+
+impl FromStr for StallRule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        let turns = |prefix: &str| match s[prefix.len()..].parse::<usize>() {
+            Ok(turns) if turns > 0 => Ok(turns),
+            _ => Err(format!("invalid number of turns in {}", s)),
+        };
+        match s {
+            "never" => Ok(StallRule::Never),
+            _ if s.starts_with("draw:") => turns("draw:").map(StallRule::Draw),
+            _ if s.starts_with("units:") => turns("units:").map(StallRule::MostUnits),
+            _ => Err(format!("unknown stall rule {}", s)),
+        }
+    }
+}
+
+/// The four straight lines of the given length, closest cells first.
+pub fn cross(length: i32) -> Vec<(i32, i32)> {
+    let mut offsets = vec![];
+    for i in 1..length + 1 {
+        offsets.extend(vec![(0, i), (0, -i), (-i, 0), (i, 0)]);
+    }
+    offsets
+}
+
+/// The four diagonals of the given length, closest cells first.
+pub fn diag(length: i32) -> Vec<(i32, i32)> {
+    let mut offsets = vec![];
+    for i in 1..length + 1 {
+        offsets.extend(vec![(i, i), (i, -i), (-i, i), (-i, -i)]);
+    }
+    offsets
+}
+
+/// The four straight lines and the four diagonals of the given length, closest cells first.
+pub fn star(length: i32) -> Vec<(i32, i32)> {
+    let mut offsets = vec![];
+    for i in 1..length + 1 {
+        offsets.extend(vec![(0, i), (0, -i), (i, 0), (-i, 0)]);
+        offsets.extend(vec![(i, i), (i, -i), (-i, i), (-i, -i)]);
+    }
+    offsets
+}
+
+impl Default for Rules {
+    /// The classic rules : gunners, mobile towers and infantrymen, killed by one shot.
+    fn default() -> Self {
+        Rules {
+            units: vec![
+                UnitRules::new("Gunner", 'G', cross(1), cross(2)),
+                UnitRules::new("MobileTower", 'T', cross(1), star(2)),
+                UnitRules::new("Infantryman", 'I', cross(2), cross(1)),
+            ],
+            friendly_fire: true,
+            turn: TurnMode::Single,
+            stall: StallRule::Never,
+            cover: 50,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An error in a rules file.
+pub struct RulesError {
+    /// The line of the error, starting at 1, 0 if the error concerns the whole file.
+    pub line: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid rules : {}", self.message),
+            l => write!(f, "invalid rules at line {} : {}", l, self.message),
+        }
+    }
+}
+
+/// Parses a list of offsets and patterns.
+fn parse_offsets(text: &str) -> Result<Vec<(i32, i32)>, String> {
+    let mut offsets = vec![];
+    for token in text.split_whitespace() {
+        let parsed = match token.find(':') {
+            Some(i) => {
+                let length = token[i + 1..]
+                    .parse::<i32>()
+                    .map_err(|_| format!("invalid length in {}", token))?;
+                match &token[..i] {
+                    "cross" => cross(length),
+                    "diag" => diag(length),
+                    "star" => star(length),
+                    p => return Err(format!("unknown pattern {}", p)),
+                }
+            }
+            None => {
+                let coords = token
+                    .split(',')
+                    .map(|c| c.parse::<i32>())
+                    .collect::<Result<Vec<i32>, _>>()
+                    .map_err(|_| format!("invalid offset {}", token))?;
+                match coords.len() {
+                    2 => vec![(coords[0], coords[1])],
+                    _ => return Err(format!("invalid offset {}", token)),
+                }
+            }
+        };
+        offsets.extend(parsed);
+    }
+    Ok(offsets)
+}
+
+impl Rules {
+    /// Parses rules written in the format described in the module documentation.
+    /// Blank lines and lines starting with # are ignored.
+    pub fn parse(text: &str) -> Result<Self, RulesError> {
+        let mut rules = Rules {
+            units: vec![],
+            friendly_fire: true,
+            turn: TurnMode::Single,
+            stall: StallRule::Never,
+            cover: 50,
+        };
+        for (i, line) in text.lines().enumerate() {
+            let err = |message: String| RulesError { line: i + 1, message };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim().to_string();
+                if name.is_empty() || rules.units.iter().any(|u| u.name == name) {
+                    return Err(err(format!("invalid or duplicated unit name {}", name)));
+                }
+                let symbol = name.chars().next().unwrap();
+                rules.units.push(UnitRules::new(&name, symbol, vec![], vec![]));
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+                None => return Err(err(format!("expected key = value, found {}", line))),
+            };
+            if key == "friendly_fire" {
+                rules.friendly_fire = value
+                    .parse()
+                    .map_err(|_| err(format!("invalid boolean {}", value)))?;
+                continue;
+            }
+            if key == "turn" {
+                rules.turn = value.parse().map_err(&err)?;
+                continue;
+            }
+            if key == "cover" {
+                rules.cover = match value.parse::<u32>() {
+                    Ok(cover) if cover <= 100 => cover,
+                    _ => return Err(err(format!("invalid hit chance {}", value))),
+                };
+                continue;
+            }
+            if key == "stall" {
+                rules.stall = value.parse().map_err(&err)?;
+                continue;
+            }
+            match (key, rules.units.last_mut()) {
+                (_, None) => return Err(err(format!("{} outside of a unit", key))),
+                ("symbol", Some(unit)) => match value.chars().count() {
+                    1 => unit.symbol = value.chars().next().unwrap(),
+                    _ => return Err(err(format!("invalid symbol {}", value))),
+                },
+                ("hit_points", Some(unit)) => match value.parse::<u32>() {
+                    Ok(hp) if hp > 0 => unit.hit_points = hp,
+                    _ => return Err(err(format!("invalid hit points {}", value))),
+                },
+                ("damage", Some(unit)) => match value.parse::<u32>() {
+                    Ok(damage) if damage > 0 => unit.damage = damage,
+                    _ => return Err(err(format!("invalid damage {}", value))),
+                },
+                ("armor", Some(unit)) => match value.parse::<u32>() {
+                    Ok(armor) => unit.armor = armor,
+                    _ => return Err(err(format!("invalid armor {}", value))),
+                },
+                ("cost", Some(unit)) => match value.parse::<u32>() {
+                    Ok(cost) if cost > 0 => unit.cost = cost,
+                    _ => return Err(err(format!("invalid cost {}", value))),
+                },
+                ("moves", Some(unit)) => unit.moves = parse_offsets(value).map_err(&err)?,
+                ("shoots", Some(unit)) => unit.shoots = parse_offsets(value).map_err(&err)?,
+                (k, _) => return Err(err(format!("unknown key {}", k))),
+            }
+        }
+        if rules.units.is_empty() {
+            return Err(RulesError {
+                line: 0,
+                message: "no unit defined".to_string(),
+            });
+        }
+        Ok(rules)
+    }
+
+    /// Reads and parses the rules in the given file.
+    pub fn load(path: &str) -> Result<Self, RulesError> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| RulesError {
+                line: 0,
+                message: format!("could not read {} : {}", path, e),
+            })?;
+        Rules::parse(&text)
+    }
+
+    /// Returns the rules of the given type of unit.
+    pub fn unit(&self, unit_type: &UnitType) -> &UnitRules {
+        &self.units[unit_type.0]
+    }
+
+    /// Returns the number of hit points a shot of the shooter removes to the target.
+    /// The armor of the target absorbs part of the damage but a shot always removes at least one
+    /// hit point.
+    pub fn damage(&self, shooter: &UnitType, target: &UnitType) -> u32 {
+        let damage = self.unit(shooter).damage;
+        damage.saturating_sub(self.unit(target).armor).max(1)
+    }
+
+    /// Returns the types of units defined by the rules.
+    pub fn unit_types(&self) -> Vec<UnitType> {
+        (0..self.units.len()).map(UnitType).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The default rules written in the file format give the same rules.
+    #[test]
+    fn test_parse_default() {
+        let text = "
+            # the classic rules
+            friendly_fire = true
+            [Gunner]
+            moves = cross:1
+            shoots = cross:2
+            [MobileTower]
+            symbol = T
+            moves = cross:1
+            shoots = star:2
+            [Infantryman]
+            moves = cross:2
+            shoots = 0,1 0,-1 -1,0 1,0
+        ";
+        assert_eq!(Rules::parse(text), Ok(Rules::default()));
+        assert_eq!(Rules::load("rules.txt"), Ok(Rules::default()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Rules::parse("# nothing").unwrap_err().line, 0);
+        assert_eq!(Rules::parse("[A]\nmoves = cross:1\nshoots = 1;2").unwrap_err().line, 3);
+        assert_eq!(Rules::parse("hit_points = 2").unwrap_err().line, 1);
+        assert_eq!(Rules::parse("[A]\n[A]").unwrap_err().line, 2);
+        let rules = Rules::parse("friendly_fire = false\n[Knight]\nhit_points = 3\nmoves = 1,2 diag:1").unwrap();
+        assert!(!rules.friendly_fire);
+        assert_eq!(rules.units[0].symbol, 'K');
+        assert_eq!(rules.units[0].hit_points, 3);
+        assert_eq!(rules.units[0].moves, vec![(1, 2), (1, 1), (1, -1), (-1, 1), (-1, -1)]);
+        assert_eq!(Rules::parse("[A]\ndamage = 0").unwrap_err().line, 2);
+        assert_eq!(Rules::parse("turn = points:0\n[A]").unwrap_err().line, 1);
+        let rules = Rules::parse("turn = points:3\n[A]\ncost = 2").unwrap();
+        assert_eq!((rules.turn, rules.units[0].cost), (TurnMode::ActionPoints(3), 2));
+        assert_eq!(Rules::parse("stall = units:20\n[A]").unwrap().stall, StallRule::MostUnits(20));
+        assert_eq!(Rules::parse("stall = draw:0\n[A]").unwrap_err().line, 1);
+        assert_eq!(Rules::parse("cover = 25\n[A]").unwrap().cover, 25);
+        assert_eq!(Rules::parse("cover = 101\n[A]").unwrap_err().line, 1);
+    }
+
+    #[test]
+    fn test_damage() {
+        let rules = Rules::parse("[Knight]\ndamage = 3\narmor = 1\n[Archer]\narmor = 5").unwrap();
+        let (knight, archer) = (UnitType(0), UnitType(1));
+        assert_eq!(rules.damage(&knight, &knight), 2);
+        assert_eq!(rules.damage(&archer, &knight), 1);
+        assert_eq!(rules.damage(&knight, &archer), 1);
+    }
+}
@@ -0,0 +1,264 @@
+//! The terrain of the grid : every cell has a ground changing what the units can do there.
+//! Maps can be written as text to play handcrafted scenarios, one line per row of the grid :
+//!
+//! ```text
+//! # the units of the first player use the symbols of the rules, those of the second
+//! # player the same symbols in lowercase.
+//! G..#..t
+//! .+~~~+.
+//! T..#..g
+//! ```
+//!
+//! The grounds are `.` plain, `#` wall, `+` cover, `~` water and `^` high ground, the units
+//! stand on plain ground.
+use std::fmt;
+
+use rand::{Rng, XorShiftRng};
+use rules::Rules;
+use unit::{Position, Unit};
+
+#[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
+/// The ground of a cell.
+pub enum Terrain {
+    /// Nothing special.
+    Plain,
+    /// No unit can stand in a wall and it can not be shot.
+    Wall,
+    /// The shots from the cells not adjacent to a unit in cover only hit it every few shots. The
+    /// game being deterministic, each shot from afar adds *Rules::cover* to the unit and the shot
+    /// bringing it to 100 hits, the surplus being kept for the next shots : with 50, every second
+    /// shot hits, with 30, three shots in ten hit. The count starts again when the unit moves.
+    Cover,
+    /// A unit in water can only move to the adjacent cells.
+    Water,
+    /// A unit on high ground shoots one cell further in every direction.
+    HighGround,
+}
+
+impl Terrain {
+    /// Returns the terrain represented by the char in a map, if any.
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            '.' => Some(Terrain::Plain),
+            '#' => Some(Terrain::Wall),
+            '+' => Some(Terrain::Cover),
+            '~' => Some(Terrain::Water),
+            '^' => Some(Terrain::HighGround),
+            _ => None,
+        }
+    }
+    /// Returns the char representing the terrain in a map.
+    pub fn to_char(&self) -> char {
+        match *self {
+            Terrain::Plain => '.',
+            Terrain::Wall => '#',
+            Terrain::Cover => '+',
+            Terrain::Water => '~',
+            Terrain::HighGround => '^',
+        }
+    }
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, Debug)]
+/// The grounds of the cells of a grid.
+pub struct Map {
+    /// The width of the grid.
+    width: usize,
+    /// The height of the grid.
+    height: usize,
+    /// The grounds, column after column.
+    cells: Vec<Terrain>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// An error in a map file.
+pub struct MapError {
+    /// The line of the error, starting at 1, 0 if the error concerns the whole file.
+    pub line: usize,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            0 => write!(f, "invalid map : {}", self.message),
+            l => write!(f, "invalid map at line {} : {}", l, self.message),
+        }
+    }
+}
+
+impl Map {
+    /// Creates a map of the given dimensions with plain ground everywhere.
+    pub fn new(width: usize, height: usize) -> Self {
+        Map {
+            width,
+            height,
+            cells: vec![Terrain::Plain; width * height],
+        }
+    }
+    /// Creates a random map of the given dimensions.
+    /// The map is symmetric in the same way as the units of *Game::new_random* so that no player
+    /// is favored, a tenth of the cells being walls and a twentieth of each other ground.
+    pub fn new_random(width: usize, height: usize, my_rand: &mut XorShiftRng) -> Self {
+        let mut map = Map::new(width, height);
+        for x in 0..width / 2 {
+            for y in 0..height {
+                let terrain = match my_rand.gen_range(0, 20) {
+                    0 | 1 => Terrain::Wall,
+                    2 => Terrain::Cover,
+                    3 => Terrain::Water,
+                    4 => Terrain::HighGround,
+                    _ => Terrain::Plain,
+                };
+                map.set(Position::new(x, y), terrain);
+                map.set(Position::new(width - x - 1, height - y - 1), terrain);
+            }
+        }
+        map
+    }
+    /// Parses a map and its units written in the format described in the module documentation.
+    /// Blank lines and lines starting with `# ` are ignored, the space telling them apart from a
+    /// row begining with a wall. The units have the types of the given rules.
+    pub fn parse(text: &str, rules: &Rules) -> Result<(Self, Vec<Vec<Unit>>), MapError> {
+        let rows = text.lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|&(_, line)| !line.is_empty() && !line.starts_with("# "))
+            .collect::<Vec<(usize, &str)>>();
+        let width = rows.first().map_or(0, |&(_, row)| row.chars().count());
+        if width == 0 {
+            return Err(MapError {
+                line: 0,
+                message: "the map is empty".to_string(),
+            });
+        }
+        let mut map = Map::new(width, rows.len());
+        let mut players = vec![vec![], vec![]];
+        for (y, &(line, row)) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(MapError {
+                    line,
+                    message: format!("expected {} cells, found {}", width, row.chars().count()),
+                });
+            }
+            for (x, c) in row.chars().enumerate() {
+                let pos = Position::new(x, y);
+                if let Some(terrain) = Terrain::from_char(c) {
+                    map.set(pos, terrain);
+                    continue;
+                }
+                let mut owner = rules.unit_types().into_iter().filter_map(|t| {
+                    let symbol = rules.unit(&t).symbol;
+                    if c == symbol {
+                        Some((t, 0))
+                    } else if c.is_lowercase() && c.to_uppercase().eq(symbol.to_uppercase()) {
+                        Some((t, 1))
+                    } else {
+                        None
+                    }
+                });
+                match owner.next() {
+                    Some((t, team)) => players[team].push(Unit::new(pos, t, team)),
+                    None => {
+                        return Err(MapError {
+                            line,
+                            message: format!("unknown ground or unit {}", c),
+                        })
+                    }
+                }
+            }
+        }
+        Ok((map, players))
+    }
+    /// Returns the width of the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Returns the height of the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Returns true if the position is in the grid.
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+    /// Returns the ground at the given position, wich must be in the grid.
+    pub fn at(&self, pos: Position) -> Terrain {
+        self.cells[pos.x * self.height + pos.y]
+    }
+    /// Sets the ground at the given position, wich must be in the grid.
+    pub fn set(&mut self, pos: Position, terrain: Terrain) -> &mut Self {
+        self.cells[pos.x * self.height + pos.y] = terrain;
+        self
+    }
+    /// Returns true if a unit can stand or be shot at the given position.
+    pub fn reachable(&self, pos: Position) -> bool {
+        self.contains(pos) && self.at(pos) != Terrain::Wall
+    }
+}
+
+impl fmt::Display for Map {
+    /// Formats the map in the format it is parsed from, without the units.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            let row = (0..self.width)
+                .map(|x| self.at(Position::new(x, y)).to_char())
+                .collect::<String>();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use unit::UnitType;
+
+    #[test]
+    fn test_parse() {
+        let text = "
+            # a small valley.
+            G..#..i
+            .+~~~^.
+            T..#..g
+        ";
+        let (map, players) = Map::parse(text, &Rules::default()).unwrap();
+        assert_eq!((map.width(), map.height()), (7, 3));
+        assert_eq!(map.at(Position::new(3, 0)), Terrain::Wall);
+        assert_eq!(map.at(Position::new(5, 1)), Terrain::HighGround);
+        assert_eq!(map.at(Position::new(6, 0)), Terrain::Plain);
+        assert_eq!(
+            players[0],
+            vec![
+                Unit::new(Position::new(0, 0), UnitType::GUNNER, 0),
+                Unit::new(Position::new(0, 2), UnitType::MOBILE_TOWER, 0),
+            ]
+        );
+        assert_eq!(
+            players[1],
+            vec![
+                Unit::new(Position::new(6, 0), UnitType::INFANTRYMAN, 1),
+                Unit::new(Position::new(6, 2), UnitType::GUNNER, 1),
+            ]
+        );
+        assert_eq!(map.to_string(), "...#...\n.+~~~^.\n...#...\n");
+        assert_eq!(Map::parse("G..\n..", &Rules::default()).unwrap_err().line, 2);
+        assert_eq!(Map::parse("G.x", &Rules::default()).unwrap_err().line, 1);
+        let valley = include_str!("../valley.txt");
+        let (map, players) = Map::parse(valley, &Rules::default()).unwrap();
+        assert_eq!((map.width(), map.height(), players[0].len(), players[1].len()), (10, 8, 3, 3));
+    }
+
+    /// The random maps are symmetric.
+    #[test]
+    fn test_random() {
+        let map = Map::new_random(10, 15, &mut XorShiftRng::new_unseeded());
+        for x in 0..10 {
+            for y in 0..15 {
+                assert_eq!(map.at(Position::new(x, y)), map.at(Position::new(9 - x, 14 - y)));
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
 //! Contains structs and methods to represent units and their actions.
 use rand::{XorShiftRng, Rng};
+use rules::Rules;
+use terrain::{Map, Terrain};
+use board::Board;
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 /// The position's struct.
@@ -23,10 +26,41 @@ impl Position {
         self.y = (self.y as i32 + y) as usize;
         self
     }
-    /// Given a game's dimension and the already used positions, returns true if the position is
+    /// Returns the distance to the other position, counting a diagonal step as one.
+    pub fn distance(&self, other: Position) -> usize {
+        let dx = (self.x as i32 - other.x as i32).abs();
+        let dy = (self.y as i32 - other.y as i32).abs();
+        dx.max(dy) as usize
+    }
+    /// Returns the cells crossed by the straight line to the target, without both ends.
+    /// The line is traced with the Bresenham algorithm so that it only goes through whole cells.
+    pub fn line_to(&self, target: Position) -> Vec<Position> {
+        let (x1, y1) = (target.x as i32, target.y as i32);
+        let (mut x, mut y) = (self.x as i32, self.y as i32);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut err = dx + dy;
+        let mut cells = vec![];
+        while (x, y) != (x1, y1) {
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+            if (x, y) != (x1, y1) {
+                cells.push(Position::new(x as usize, y as usize));
+            }
+        }
+        cells
+    }
+    /// Given a game's map and the occupants of its cells, returns true if the position is
     /// available.
-    pub fn valid(&self, width: usize, height: usize, positions: &Vec<Position>) -> bool {
-        self.x < width && self.y < height && !positions.contains(&self)
+    pub fn valid(&self, map: &Map, board: &Board) -> bool {
+        map.reachable(*self) && !board.occupied(*self)
     }
 }
 
@@ -36,35 +70,59 @@ impl Position {
 pub struct Action {
     /// The position of the unit making that action.
     pub unit: Position,
-    /// The position at wich the unit will move.
+    /// The position at wich the unit will move, its own position if it stays.
     pub mv: Position,
-    /// The position at wich the unit will shoot.
-    pub shoot: Position,
+    /// The position at wich the unit will shoot, if it shoots.
+    pub shoot: Option<Position>,
 }
 impl Action {
     /// Creates a new action with the given parameters.
+    /// The unit moves then shoots, a move of (0, 0) making it shoot without moving.
     pub fn new(unit: Position, mv: (i32, i32), shoot: (i32, i32)) -> Self {
         Action {
-            unit: unit,
-            mv: unit.clone().move_by(mv).clone(),
-            shoot: unit.clone().move_by(shoot).clone(),
+            shoot: Some(*unit.clone().move_by(shoot)),
+            ..Action::moving(unit, mv)
         }
     }
+    /// Creates an action where the unit moves without shooting.
+    pub fn moving(unit: Position, mv: (i32, i32)) -> Self {
+        let mut destination = unit;
+        destination.move_by(mv);
+        Action {
+            unit,
+            mv: destination,
+            shoot: None,
+        }
+    }
+    /// Creates an action where the unit holds its position without shooting.
+    pub fn hold(unit: Position) -> Self {
+        Action::moving(unit, (0, 0))
+    }
     pub fn caster_pos(&self) -> Position {
         self.unit
     }
 }
 
+#[derive(Clone, Hash, PartialEq, Eq, Debug, Default)]
+/// The actions of a player during one turn, in the order they are made.
+/// Depending on the *TurnMode* of the rules, a turn has one action or one per unit acting.
+pub struct Turn {
+    /// The actions of the turn.
+    pub actions: Vec<Action>,
+}
 
-#[derive(Hash, Clone, PartialEq, Eq, Debug)]
-/// The enum for the different units.
-pub enum UnitType {
-    /// The infantry (moves a lot but does not shoot far.
-    Infantryman,
-    /// The gunner (moves slowly but shoots far.
-    Gunner,
-    /// The mobile tower (shoots far around and moves slowly).
-    MobileTower,
+
+#[derive(Hash, Clone, Copy, PartialEq, Eq, Debug)]
+/// The type of an unit, its index in the types of the *Rules*.
+pub struct UnitType(pub usize);
+
+impl UnitType {
+    /// The gunner of the classic rules (moves slowly but shoots far).
+    pub const GUNNER: UnitType = UnitType(0);
+    /// The mobile tower of the classic rules (shoots far around and moves slowly).
+    pub const MOBILE_TOWER: UnitType = UnitType(1);
+    /// The infantry of the classic rules (moves a lot but does not shoot far).
+    pub const INFANTRYMAN: UnitType = UnitType(2);
 }
 
 #[derive(Hash, Clone, PartialEq, Eq, Debug)]
@@ -76,28 +134,42 @@ pub struct Unit {
     pub pos: Position,
     /// The team of the unit.
     pub team: usize,
+    /// The hit points left to the unit.
+    pub hp: u32,
+    /// True if the unit already acted during the current turn.
+    pub acted: bool,
+    /// What the shots from afar added to the unit in cover since its last hit or move, see
+    /// *Terrain::Cover*.
+    pub grazed: u32,
 }
 
 impl Unit {
     /// Creates a new unit.
+    /// It has one hit point until it joins a game, wich gives it the hit points of its type.
     pub fn new(pos: Position, typ: UnitType, team: usize) -> Self {
         Unit {
             unit_type: typ,
             pos: pos,
             team: team,
+            hp: 1,
+            acted: false,
+            grazed: 0,
         }
     }
     /// Reverse an unit :
     /// Takes an unit at a given position with a team and
     /// returns a new unit at a symmetrical position on the map for the other team.
     pub fn reverse(&self, width: usize, heigth: usize) -> Unit {
-        Unit::new(
-            Position::new(width - self.pos.x - 1, heigth - self.pos.y - 1),
-            self.unit_type.clone(),
-            1 - self.team,
-        )
+        Unit {
+            hp: self.hp,
+            ..Unit::new(
+                Position::new(width - self.pos.x - 1, heigth - self.pos.y - 1),
+                self.unit_type,
+                1 - self.team,
+            )
+        }
     }
-    /// Creates a new random unit.
+    /// Creates a new random unit of one of the types of the rules.
     /// The width_start and width_end represents the range of x positions in wich the unit can be.
     /// The unit's y position will be in [0, height]
     /// Also takes the team of the unit and a random generator.
@@ -105,112 +177,94 @@ impl Unit {
         width_start: usize,
         width_end: usize,
         height: usize,
+        rules: &Rules,
         my_rand: &mut XorShiftRng,
         team: usize,
     ) -> Self {
+        let unit_type = *my_rand.choose(&rules.unit_types()).unwrap();
         Unit {
-            unit_type: my_rand
-                .choose(
-                    &[
-                        UnitType::Gunner,
-                        UnitType::MobileTower,
-                        UnitType::Infantryman,
-                    ],
-                )
-                .unwrap()
-                .clone(),
+            unit_type: unit_type,
             pos: Position::new(
                 my_rand.gen_range(width_start, width_end),
                 my_rand.gen_range(0, height),
             ),
             team: team,
+            hp: rules.unit(&unit_type).hit_points,
+            acted: false,
+            grazed: 0,
         }
     }
     /// Returns a char corresponding to the type of the unit.
-    pub fn unit_char(&self) -> char {
-        match self.unit_type {
-            UnitType::Gunner => 'G',
-            UnitType::Infantryman => 'I',
-            UnitType::MobileTower => 'T',
-        }
+    pub fn unit_char(&self, rules: &Rules) -> char {
+        rules.unit(&self.unit_type).symbol
     }
     /// Returns the possible moves for the given unit.
-    /// width and height are the dimensions of the grid and
-    /// pos are the position that are already occupied.
-    pub fn actions(&self, width: usize, height: usize, positions: &Vec<Position>) -> Vec<Action> {
+    /// map is the terrain of the grid and board the occupants of its cells.
+    ///
+    /// The unit holds its position or moves, then shoots at one of the units it can hurt or not :
+    /// the ennemies and, with friendly fire, its allies.
+    /// The unit can not move into walls. From water it only moves to the adjacent cells.
+    pub fn actions(&self, rules: &Rules, map: &Map, board: &Board) -> Vec<Action> {
         let mut actions = vec![];
-        for dep in self.moves() {
-            if self.pos.clone().move_by(dep).valid(
-                width,
-                height,
-                positions,
-            )
-            {
-                let mut new_act = self.clone();
-                new_act.pos.move_by(dep);
-                for shoot in new_act.shoots() {
-                    if new_act.pos.clone().move_by(shoot).valid(
-                        width,
-                        height,
-                        &vec![],
-                    )
-                    {
-                        actions.push(Action::new(
-                            self.pos,
-                            dep,
-                            (shoot.0 + dep.0, shoot.1 + dep.1),
-                        ));
+        let in_water = map.at(self.pos) == Terrain::Water;
+        let moves = self.moves(rules).iter().filter(|&&dep| dep != (0, 0));
+        for &dep in [(0, 0)].iter().chain(moves) {
+            if in_water && (dep.0.abs() > 1 || dep.1.abs() > 1) {
+                continue;
+            }
+            let destination = *self.pos.clone().move_by(dep);
+            if dep == (0, 0) || destination.valid(map, board) {
+                actions.push(Action::moving(self.pos, dep));
+                for aim in self.aims(rules, map, destination, board) {
+                    let target = board.get(aim).map(|(team, _)| team);
+                    let hurt = target.is_some_and(|t| t != self.team || rules.friendly_fire);
+                    if aim != self.pos && hurt {
+                        let mut action = Action::moving(self.pos, dep);
+                        action.shoot = Some(aim);
+                        actions.push(action);
                     }
                 }
             }
         }
         actions
     }
+    /// Returns the cells the unit can shoot at after moving to the given position.
+    /// The unit can not shoot walls and from high ground it shoots one cell further.
+    /// The unit only shoots at the cells it sees : no wall and no other unit stands in the way.
+    pub fn aims(&self, rules: &Rules, map: &Map, from: Position, board: &Board) -> Vec<Position> {
+        let mut moved = self.clone();
+        moved.pos = from;
+        moved
+            .shoots(rules, map)
+            .into_iter()
+            .map(|shoot| *from.clone().move_by(shoot))
+            .filter(|&aim| map.reachable(aim) && self.sees(from, aim, map, board))
+            .collect()
+    }
+    /// Returns true if the unit, moved at *from*, can see the target.
+    /// The line between them must cross no wall and no unit, the unit having left its cell.
+    fn sees(&self, from: Position, target: Position, map: &Map, board: &Board) -> bool {
+        from.line_to(target).iter().all(|&cell| {
+            map.at(cell) != Terrain::Wall && (cell == self.pos || !board.occupied(cell))
+        })
+    }
     /// Returns the possible deployments for this unit.
-    pub fn moves(&self) -> Vec<(i32, i32)> {
-        match self.unit_type {
-            UnitType::Infantryman => {
-                let mut mvs = vec![];
-                for i in 1..3 {
-                    mvs.push((0, i));
-                    mvs.push((0, -i));
-                    mvs.push((-i, 0));
-                    mvs.push((i, 0));
-                }
-                mvs
-            }
-            _ => vec![(0, 1), (0, -1), (-1, 0), (1, 0)],
-        }
+    pub fn moves<'a>(&self, rules: &'a Rules) -> &'a [(i32, i32)] {
+        &rules.unit(&self.unit_type).moves
     }
     /// Returns the possible shooting targets for this unit at its position.
-    fn shoots(&self) -> Vec<(i32, i32)> {
-        match self.unit_type {
-            UnitType::Infantryman => vec![(0, 1), (0, -1), (-1, 0), (1, 0)],
-            UnitType::Gunner => {
-                let mut mvs = vec![];
-                for i in 1..3 {
-                    mvs.push((0, i));
-                    mvs.push((0, -i));
-                    mvs.push((-i, 0));
-                    mvs.push((i, 0));
-                }
-                mvs
-            }
-            UnitType::MobileTower => {
-                let mut mvs = vec![];
-                for i in 1..3 {
-                    mvs.push((0, i));
-                    mvs.push((0, -i));
-                    mvs.push((i, 0));
-                    mvs.push((-i, 0));
-                    mvs.push((i, i));
-                    mvs.push((i, -i));
-                    mvs.push((-i, i));
-                    mvs.push((-i, -i));
-                }
-                mvs
-            }
+    /// On high ground, every target can also be shot one cell further.
+    fn shoots(&self, rules: &Rules, map: &Map) -> Vec<(i32, i32)> {
+        let mut shoots = rules.unit(&self.unit_type).shoots.clone();
+        if map.at(self.pos) == Terrain::HighGround {
+            let further = shoots
+                .iter()
+                .map(|&(x, y)| (x + x.signum(), y + y.signum()))
+                .filter(|s| !shoots.contains(s))
+                .collect::<Vec<(i32, i32)>>();
+            shoots.extend(further);
         }
+        shoots
     }
 }
 
@@ -222,67 +276,78 @@ mod test {
     /// when generating moves..
     #[test]
     fn test_infantry() {
-        let mut infantry = Unit::new(Position::new(0, 0), UnitType::Infantryman, 0);
-        let last_act = infantry.actions(10, 10, vec![]);
+        let infantry = Unit::new(Position::new(0, 0), UnitType::INFANTRYMAN, 0);
+        let last_act = infantry.actions(&Rules::default(), &Map::new(10, 10), &Board::new(10, 10, &[]));
         let mvs: Vec<Position> = last_act.iter().map(|x| x.mv).collect();
-        // Every move occur twice because it goes with a possible shot.
+        // Without targets, the unit holds or moves without shooting.
+        assert!(last_act.iter().all(|x| x.shoot.is_none()));
         assert_eq!(
             mvs,
             vec![
+                Position { x: 0, y: 0 },
                 Position { x: 0, y: 1 },
-                Position { x: 0, y: 1 },
-                Position { x: 1, y: 0 },
                 Position { x: 1, y: 0 },
                 Position { x: 0, y: 2 },
-                Position { x: 0, y: 2 },
-                Position { x: 2, y: 0 },
                 Position { x: 2, y: 0 },
             ]
         );
     }
     #[test]
     fn test_mobile_tower() {
-        let mut tower = Unit::new(Position::new(0, 0), UnitType::MobileTower, 0);
-        let last_act = tower.actions(10, 10, vec![]);
-        let mvs: Vec<Position> = last_act.iter().map(|x| x.shoot).collect();
-        // Every move occur twice because it goes with a possible shot.
+        let tower = Unit::new(Position::new(0, 0), UnitType::MOBILE_TOWER, 0);
+        let ennemy = Position::new(2, 2);
+        let board = Board::new(10, 10, &[vec![tower.clone()], vec![Unit::new(ennemy, UnitType::GUNNER, 1)]]);
+        let last_act = tower.actions(&Rules::default(), &Map::new(10, 10), &board);
+        // The tower only reaches the ennemy on its diagonal if it holds its position.
         assert_eq!(
-            mvs,
+            last_act,
             vec![
-                Position { x: 0, y: 1 },
-                Position { x: 1, y: 0 },
-                Position { x: 1, y: 1 },
-                Position { x: 0, y: 2 },
-                Position { x: 2, y: 0 },
-                Position { x: 2, y: 2 },
-                Position { x: 0, y: 1 },
-                Position { x: 1, y: 0 },
-                Position { x: 1, y: 1 },
-                Position { x: 0, y: 2 },
-                Position { x: 2, y: 0 },
-                Position { x: 2, y: 2 },
+                Action::hold(tower.pos),
+                Action::new(tower.pos, (0, 0), (2, 2)),
+                Action::moving(tower.pos, (0, 1)),
+                Action::moving(tower.pos, (1, 0)),
             ]
         );
     }
     #[test]
     fn test_gunner() {
-        let mut infantry = Unit::new(Position::new(0, 0), UnitType::Gunner, 0);
-        let last_act = infantry.actions(10, 10, vec![]);
-        println!("moves : {:?}", infantry.moves());
-        let mvs: Vec<Position> = last_act.iter().map(|x| x.mv).collect();
-        // Every move occur twice because it goes with a possible shot.
+        let gunner = Unit::new(Position::new(0, 0), UnitType::GUNNER, 0);
+        let ennemy = Position::new(0, 2);
+        let board = Board::new(10, 10, &[vec![gunner.clone()], vec![Unit::new(ennemy, UnitType::GUNNER, 1)]]);
+        let last_act = gunner.actions(&Rules::default(), &Map::new(10, 10), &board);
+        let shots = last_act.iter().filter(|x| x.shoot == Some(ennemy)).map(|x| x.mv).collect::<Vec<Position>>();
+        // The gunner shoots the ennemy from where it stands or after moving toward it.
+        assert_eq!(last_act.len(), 5);
+        assert_eq!(shots, vec![Position::new(0, 0), Position::new(0, 1)]);
+    }
+    #[test]
+    fn test_line() {
+        let origin = Position::new(0, 0);
+        assert_eq!(origin.line_to(Position::new(1, 1)), vec![]);
+        assert_eq!(origin.line_to(Position::new(2, 2)), vec![Position::new(1, 1)]);
+        assert_eq!(Position::new(0, 2).line_to(origin), vec![Position::new(0, 1)]);
         assert_eq!(
-            mvs,
-            vec![
-                Position { x: 0, y: 1 },
-                Position { x: 0, y: 1 },
-                Position { x: 0, y: 1 },
-                Position { x: 0, y: 1 },
-                Position { x: 1, y: 0 },
-                Position { x: 1, y: 0 },
-                Position { x: 1, y: 0 },
-                Position { x: 1, y: 0 },
-            ]
+            origin.line_to(Position::new(3, 1)),
+            vec![Position::new(1, 0), Position::new(2, 1)]
         );
     }
+    /// The diagonal shots of the tower are blocked by the units and the walls in between, but not
+    /// by the cell the tower left.
+    #[test]
+    fn test_line_of_sight() {
+        let tower = Unit::new(Position::new(5, 5), UnitType::MOBILE_TOWER, 0);
+        let mut map = Map::new(10, 10);
+        map.set(Position::new(4, 3), Terrain::Wall);
+        let blocker = Unit::new(Position::new(6, 5), UnitType::GUNNER, 1);
+        let board = Board::new(10, 10, &[vec![tower.clone()], vec![blocker]]);
+        let shots = tower.aims(&Rules::default(), &map, Position::new(5, 4), &board);
+        assert!(shots.contains(&Position::new(6, 5)));
+        assert!(!shots.contains(&Position::new(7, 6)));
+        assert!(!shots.contains(&Position::new(4, 3)));
+        assert!(!shots.contains(&Position::new(3, 2)));
+        assert!(shots.contains(&Position::new(7, 2)));
+        assert!(shots.contains(&Position::new(3, 6)));
+        assert!(shots.contains(&Position::new(5, 6)));
+        assert_eq!(shots.len(), 13);
+    }
 }
@@ -1,9 +1,10 @@
 //! Gameboard controller.
 
+use std::time::Duration;
 use piston::input::GenericEvent;
 use mission3::game::Game;
 use mission3::unit::Action;
-use mission3::ai::AI;
+use mission3::ai::{AI, Budget};
 use mission3::unit::Position;
 
 
@@ -36,7 +37,11 @@ pub struct GameboardController {
 
 impl GameboardController {
     /// Creates a new gameboard controller.
-    pub fn new(state: Game, ais: Vec<Box<AI>>) -> GameboardController {
+    /// The ais play a move every half second, they search for a bit less.
+    pub fn new(state: Game, mut ais: Vec<Box<AI>>) -> GameboardController {
+        for ai in ais.iter_mut() {
+            ai.budget(Budget::Time(Duration::from_millis(400)));
+        }
         GameboardController {
             gameboard: state,
             selected_cell: None,
@@ -56,13 +61,7 @@ impl GameboardController {
         if self.selected_cell.is_some() {
             let sel = self.selected_cell.unwrap();
             match self.gameboard.at(Position::new(sel[0], sel[1])) {
-                Some(u) => {
-                    u.actions(
-                        self.gameboard.width(),
-                        self.gameboard.height(),
-                        &self.gameboard.positions(),
-                    )
-                }
+                Some(u) => self.gameboard.actions(u),
                 None => vec![],
             }
         } else {
@@ -141,12 +140,13 @@ impl GameboardController {
         if self.playing {
             if self.time_since_last_move > 0.5 {
                 self.time_since_last_move -= 0.5;
-                if self.gameboard.player(self.player).len() == 0 {
+                if self.gameboard.outcome().is_some() {
                     return;
                 }
+                self.player = self.gameboard.current_player();
                 let mv = &self.ais[self.player].play(&self.gameboard, self.player);
                 self.gameboard.apply_move(mv);
-                self.player = 1 - self.player;
+                self.player = self.gameboard.current_player();
             }
         }
     }
@@ -5,6 +5,8 @@ use graphics::{Context, Graphics};
 use graphics::{Image, Line, Rectangle, Transformed};
 
 use GameboardController;
+use mission3::terrain::Terrain;
+use mission3::unit::Position;
 
 /// Stores gameboard view settings.
 pub struct GameboardViewSettings {
@@ -223,6 +225,16 @@ impl GameboardView {
     ) where
         C: CharacterCache<Texture = G::Texture>,
     {
+        // Draw the terrain.
+        let map = controller.gameboard.map();
+        for x in 0..map.width() {
+            for y in 0..map.height() {
+                let terrain = map.at(Position::new(x, y));
+                if terrain != Terrain::Plain {
+                    self.draw_in_cell(glyphs, c, g, settings, (x, y), terrain.to_char());
+                }
+            }
+        }
         // Draw characters.
         for player in 0..2 {
             for i in controller.gameboard.player(player) {
@@ -239,7 +251,7 @@ impl GameboardView {
                     g,
                     settings,
                     (i.pos.x, i.pos.y),
-                    i.unit_char(),
+                    i.unit_char(controller.gameboard.rules()),
                 );
             }
         }
@@ -264,14 +276,10 @@ impl GameboardView {
 
         if controller.coloring {
 
+            for cell in controller.gameboard.reach(controller.player) {
+                self.draw_cell(settings.cell_attack, (cell.x, cell.y), c, g, settings);
+            }
             for action in controller.gameboard.moves(controller.player) {
-                self.draw_cell(
-                    settings.cell_attack,
-                    (action.shoot.x, action.shoot.y),
-                    c,
-                    g,
-                    settings,
-                );
                 self.draw_cell(
                     settings.cell_move,
                     (action.mv.x, action.mv.y),
@@ -283,13 +291,9 @@ impl GameboardView {
         }
         if controller.selected_cell.is_some() {
             for action in controller.selected_actions() {
-                self.draw_cell(
-                    settings.cell_attack,
-                    (action.shoot.x, action.shoot.y),
-                    c,
-                    g,
-                    settings,
-                );
+                if let Some(shoot) = action.shoot {
+                    self.draw_cell(settings.cell_attack, (shoot.x, shoot.y), c, g, settings);
+                }
                 self.draw_cell(
                     settings.cell_move,
                     (action.mv.x, action.mv.y),